@@ -0,0 +1,108 @@
+//! The agenda: tasks with due dates grouped by the day they are due.
+
+use chrono::NaiveDate;
+
+use crate::date::DateFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Group {
+    Overdue,
+    Today,
+    Tomorrow,
+    /// A day of the coming week after tomorrow.
+    Day(NaiveDate),
+    Later,
+}
+
+impl Group {
+    pub fn of(due: DateFormat, now: DateFormat) -> Group {
+        if due < now {
+            return Group::Overdue;
+        }
+        let today = now.naive_local().date();
+        let day = due.naive_local().date();
+        match (day - today).num_days() {
+            0 => Group::Today,
+            1 => Group::Tomorrow,
+            2..=6 => Group::Day(day),
+            _ => Group::Later,
+        }
+    }
+
+    pub fn heading(&self) -> String {
+        match self {
+            Group::Overdue => "Overdue".into(),
+            Group::Today => "Today".into(),
+            Group::Tomorrow => "Tomorrow".into(),
+            Group::Day(day) => day.format("%A %m-%d").to_string(),
+            Group::Later => "Later".into(),
+        }
+    }
+
+    /// How the due date is shown: only the time for days of the coming week.
+    pub fn format_due(&self, due: DateFormat) -> String {
+        match self {
+            Group::Overdue | Group::Later => due.format("%Y-%m-%d %H:%M").to_string(),
+            _ => due.format("%H:%M").to_string(),
+        }
+    }
+}
+
+/// Groups the items by their due date, keeping the groups and the items in each in order.
+/// Items due after `end` are left out.
+pub fn group<T>(
+    mut items: Vec<(DateFormat, T)>,
+    now: DateFormat,
+    end: Option<DateFormat>,
+) -> Vec<(Group, Vec<(DateFormat, T)>)> {
+    items.retain(|(due, _)| end.is_none_or(|end| *due <= end));
+    items.sort_by_key(|(due, _)| *due);
+
+    let mut groups: Vec<(Group, Vec<(DateFormat, T)>)> = Vec::new();
+    for (due, item) in items {
+        let group = Group::of(due, now);
+        match groups.last_mut() {
+            Some((last, items)) if *last == group => items.push((due, item)),
+            _ => groups.push((group, vec![(due, item)])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_group() {
+        // a wednesday
+        let now = Local.with_ymd_and_hms(2026, 10, 14, 10, 0, 0).unwrap();
+        let at = |d, h| Local.with_ymd_and_hms(2026, 10, d, h, 0, 0).unwrap();
+
+        assert_eq!(Group::of(at(14, 9), now), Group::Overdue);
+        assert_eq!(Group::of(at(14, 23), now), Group::Today);
+        assert_eq!(Group::of(at(15, 0), now), Group::Tomorrow);
+        assert_eq!(Group::of(at(20, 12), now).heading(), "Tuesday 10-20");
+        assert_eq!(Group::of(at(21, 12), now), Group::Later);
+
+        let groups = group(
+            vec![
+                (at(21, 12), "c"),
+                (at(14, 12), "b"),
+                (at(14, 11), "a"),
+                (at(30, 0), "d"),
+            ],
+            now,
+            Some(at(22, 0)),
+        );
+        let groups: Vec<(Group, Vec<&str>)> = groups
+            .into_iter()
+            .map(|(group, items)| (group, items.into_iter().map(|(_, i)| i).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![(Group::Today, vec!["a", "b"]), (Group::Later, vec!["c"])]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Parsing of command arguments and `--flags`.
+//! The same parser is used for the command line and the interactive mode.
+
+use std::collections::HashMap;
+
+/// Flags that don't take a value.
+const SWITCHES: &[&str] = &["help", "json", "tree"];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Args {
+    pub positional: Vec<String>,
+    flags: HashMap<String, String>,
+}
+
+impl Args {
+    /// Parses a list of words into positional arguments and flags.
+    /// Flags can be passed as `--name value` or `--name=value`.
+    pub fn parse<S: AsRef<str>>(words: &[S]) -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut words = words.iter().map(|w| w.as_ref());
+
+        while let Some(word) = words.next() {
+            if let Some(flag) = word.strip_prefix("--") {
+                if let Some((name, value)) = flag.split_once('=') {
+                    args.flags.insert(name.to_string(), value.to_string());
+                } else if SWITCHES.contains(&flag) {
+                    args.flags.insert(flag.to_string(), String::new());
+                } else if let Some(value) = words.next() {
+                    args.flags.insert(flag.to_string(), value.to_string());
+                } else {
+                    return Err(format!("Missing value for --{}", flag));
+                }
+            } else {
+                args.positional.push(word.to_string());
+            }
+        }
+
+        Ok(args)
+    }
+
+    /// Returns the first positional argument or an empty string.
+    pub fn arg(&self) -> &str {
+        self.positional.first().map(|s| s.as_str()).unwrap_or("")
+    }
+
+    /// Returns the value of the first flag (from `names`) that is present.
+    pub fn get(&self, names: &[&str]) -> Option<&str> {
+        names
+            .iter()
+            .find_map(|name| self.flags.get(*name))
+            .map(|s| s.as_str())
+    }
+
+    pub fn has(&self, names: &[&str]) -> bool {
+        self.get(names).is_some()
+    }
+
+    /// Returns an error for the first flag that isn't one of `allowed`. `--help` is always allowed.
+    pub fn check_flags(&self, allowed: &[&str]) -> Result<(), String> {
+        let mut unknown: Vec<&str> = self
+            .flags
+            .keys()
+            .map(String::as_str)
+            .filter(|name| *name != "help" && !allowed.contains(name))
+            .collect();
+        unknown.sort_unstable();
+        match unknown.first() {
+            None => Ok(()),
+            Some(name) if allowed.is_empty() => Err(format!(
+                "Unknown flag --{}! (the command has no flags)",
+                name
+            )),
+            Some(name) => Err(format!(
+                "Unknown flag --{}! (use one of: --{})",
+                name,
+                allowed.join(", --")
+            )),
+        }
+    }
+
+    /// Removes a flag and returns its value. Used for global flags that aren't passed to commands.
+    pub fn take(&mut self, name: &str) -> Option<String> {
+        self.flags.remove(name)
+    }
+}
+
+impl std::fmt::Display for Args {
+    /// Formats the arguments so that they can be parsed again.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let quote = |word: &str| {
+            if word.is_empty() || word.contains(char::is_whitespace) {
+                format!("\"{}\"", word)
+            } else {
+                word.to_string()
+            }
+        };
+
+        let mut words: Vec<String> = self.positional.iter().map(|w| quote(w)).collect();
+        let mut flags: Vec<_> = self.flags.iter().collect();
+        flags.sort();
+        for (name, value) in flags {
+            if SWITCHES.contains(&name.as_str()) {
+                words.push(format!("--{}", name));
+            } else {
+                words.push(format!("--{} {}", name, quote(value)));
+            }
+        }
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+/// Splits a line into words separated by whitespace.
+/// Words can be quoted with `"` or `'` to include whitespace.
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            None => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        return Err("Unclosed quote!".into());
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words(r#"add "Fix build" --due '2d 3h'"#).unwrap(),
+            vec!["add", "Fix build", "--due", "2d 3h"]
+        );
+        assert_eq!(split_words("  a   b ").unwrap(), vec!["a", "b"]);
+        assert_eq!(split_words(r#"add """#).unwrap(), vec!["add", ""]);
+        assert!(split_words(r#"add "Fix"#).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = Args::parse(&["Fix build", "--importance", "2", "--due=3d", "--help"]).unwrap();
+        assert_eq!(args.arg(), "Fix build");
+        assert_eq!(args.get(&["importance"]), Some("2"));
+        assert_eq!(args.get(&["desc", "due"]), Some("3d"));
+        assert!(args.has(&["help"]));
+        assert!(!args.has(&["desc"]));
+
+        assert!(Args::parse(&["--due"]).is_err(), "Flags need a value");
+    }
+
+    #[test]
+    fn test_check_flags() {
+        let args = Args::parse(&["1", "--imporance", "3", "--help"]).unwrap();
+        assert_eq!(
+            args.check_flags(&["importance", "due"]),
+            Err("Unknown flag --imporance! (use one of: --importance, --due)".into())
+        );
+        assert_eq!(
+            args.check_flags(&[]),
+            Err("Unknown flag --imporance! (the command has no flags)".into())
+        );
+        assert_eq!(args.check_flags(&["imporance"]), Ok(()));
+    }
+}
@@ -0,0 +1,160 @@
+//! Crash-safe writing of files and rotating backups of the save file.
+
+use chrono::{Duration, Local, NaiveDateTime, TimeZone};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::date::DateFormat;
+
+/// How many backups are kept.
+pub const MAX_BACKUPS: usize = 10;
+/// How many hours pass before the next backup is taken, so that running many commands
+/// doesn't replace all the older backups.
+pub const BACKUP_INTERVAL_HOURS: i64 = 1;
+pub const BACKUP_DIR_NAME: &str = "backups";
+const BACKUP_DATE_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+
+pub struct Backup {
+    pub path: PathBuf,
+    pub date: DateFormat,
+}
+
+/// Writes the contents to a temporary file and renames it over `path`,
+/// so the file is never left half written.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    if let Err(e) = std::fs::rename(&tmp_path, path) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    // make sure the rename itself is persisted
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = std::fs::File::open(if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        }) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+fn backup_dir(save_file: &Path) -> PathBuf {
+    save_file.with_file_name(BACKUP_DIR_NAME)
+}
+
+fn backup_prefix(save_file: &Path) -> String {
+    let stem = save_file.file_stem().unwrap_or_default();
+    format!("{}-", stem.to_string_lossy())
+}
+
+/// Copies the save file to the backup directory and removes the oldest backups.
+/// Does nothing if the newest backup was taken less than `BACKUP_INTERVAL_HOURS` ago.
+pub fn create_backup(save_file: &Path) -> std::io::Result<()> {
+    match std::fs::metadata(save_file) {
+        Ok(metadata) if metadata.len() > 0 => {}
+        // nothing worth backing up
+        _ => return Ok(()),
+    }
+    if let Some(newest) = list_backups(save_file).first() {
+        if Local::now() - newest.date < Duration::hours(BACKUP_INTERVAL_HOURS) {
+            return Ok(());
+        }
+    }
+
+    let dir = backup_dir(save_file);
+    std::fs::create_dir_all(&dir)?;
+    let name = format!(
+        "{}{}.json",
+        backup_prefix(save_file),
+        Local::now().format(BACKUP_DATE_FORMAT)
+    );
+    let contents = std::fs::read(save_file)?;
+    write_atomic(&dir.join(name), &contents)?;
+
+    for old in list_backups(save_file).into_iter().skip(MAX_BACKUPS) {
+        std::fs::remove_file(old.path)?;
+    }
+    Ok(())
+}
+
+/// Returns the backups of the save file, newest first.
+pub fn list_backups(save_file: &Path) -> Vec<Backup> {
+    let prefix = backup_prefix(save_file);
+    let entries = match std::fs::read_dir(backup_dir(save_file)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut backups: Vec<Backup> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            let date = name.strip_prefix(&prefix)?.strip_suffix(".json")?;
+            let date = NaiveDateTime::parse_from_str(date, BACKUP_DATE_FORMAT).ok()?;
+            let date = Local.from_local_datetime(&date).earliest()?;
+            Some(Backup { path, date })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.date));
+    backups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backups() {
+        let dir = std::env::temp_dir().join(format!("stuff-backup-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let save_file = dir.join("saved_stuff.json");
+
+        create_backup(&save_file).unwrap();
+        assert!(
+            list_backups(&save_file).is_empty(),
+            "Missing files aren't backed up"
+        );
+
+        write_atomic(&save_file, b"[]").unwrap();
+        assert_eq!(std::fs::read(&save_file).unwrap(), b"[]");
+        create_backup(&save_file).unwrap();
+
+        let backups = list_backups(&save_file);
+        assert_eq!(backups.len(), 1);
+        assert_eq!(std::fs::read(&backups[0].path).unwrap(), b"[]");
+
+        write_atomic(&save_file, b"[1]").unwrap();
+        create_backup(&save_file).unwrap();
+        assert_eq!(
+            list_backups(&save_file).len(),
+            1,
+            "Only one backup is taken per interval"
+        );
+
+        // as if the backup was taken before the interval
+        let old = Local::now() - Duration::hours(BACKUP_INTERVAL_HOURS) - Duration::minutes(1);
+        let old_path = backup_dir(&save_file).join(format!(
+            "{}{}.json",
+            backup_prefix(&save_file),
+            old.format(BACKUP_DATE_FORMAT)
+        ));
+        std::fs::rename(&backups[0].path, old_path).unwrap();
+        create_backup(&save_file).unwrap();
+        let backups = list_backups(&save_file);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(std::fs::read(&backups[0].path).unwrap(), b"[1]");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
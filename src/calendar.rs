@@ -0,0 +1,176 @@
+//! A month calendar of due dates.
+
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+
+use crate::date::parse_month;
+use crate::task::importance_color;
+
+/// The width of a day in the grid: the day and the number of tasks, e.g. ` 24(3) `.
+const CELL_WIDTH: usize = 7;
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// The tasks due on a day: how many there are and the highest importance among them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DayTasks {
+    pub count: usize,
+    pub max_importance: u8,
+}
+
+/// Parses a month given as `2026-11`, `nov` or `november` (in the year of `today`).
+/// Returns None if the argument isn't a month, and an error if the year is out of range.
+pub fn parse_year_month(arg: &str, today: NaiveDate) -> Result<Option<(i32, u32)>, String> {
+    let arg = arg.trim().to_lowercase();
+    if let Some((year, month)) = arg.split_once('-') {
+        let year = year.parse().ok();
+        let month = month.parse().ok().filter(|m| (1..=12).contains(m));
+        let (Some(year), Some(month)) = (year, month) else {
+            return Ok(None);
+        };
+        if NaiveDate::from_ymd_opt(year, month, 1).is_none() {
+            return Err(format!("The year {} is out of range!", year));
+        }
+        return Ok(Some((year, month)));
+    }
+    Ok(parse_month(&arg).map(|month| (today.year(), month)))
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    (28..=31)
+        .rev()
+        .find(|&day| NaiveDate::from_ymd_opt(year, month, day).is_some())
+        .unwrap_or(28)
+}
+
+/// Renders the month as a grid with weeks starting on monday.
+/// Days with tasks show their count in the color of the most important task; today is highlighted.
+pub fn render(
+    year: i32,
+    month: u32,
+    tasks: &HashMap<u32, DayTasks>,
+    today: NaiveDate,
+) -> Vec<String> {
+    let title = format!("{} {}", MONTH_NAMES[month as usize - 1], year);
+    let width = CELL_WIDTH * 7;
+    let mut lines = vec![
+        format!("\x1b[1m{:^width$}\x1b[0m", title, width = width),
+        ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"]
+            .iter()
+            .map(|day| format!("{:<width$}", format!("{:>3}", day), width = CELL_WIDTH))
+            .collect::<String>()
+            .trim_end()
+            .to_string(),
+    ];
+
+    let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+        return lines;
+    };
+    let offset = first.weekday().num_days_from_monday() as usize;
+    let mut line = " ".repeat(CELL_WIDTH * offset);
+    let days = (1..=days_in_month(year, month)).filter_map(|day| first.with_day(day));
+    for date in days {
+        let day = date.day();
+        let number = if date == today {
+            format!("\x1b[7m{:>3}\x1b[0m", day)
+        } else {
+            format!("{:>3}", day)
+        };
+        let count = match tasks.get(&day) {
+            Some(day_tasks) if day_tasks.count > 0 => {
+                // the count has to fit in the cell
+                let count = if day_tasks.count > 99 {
+                    "99+".to_string()
+                } else {
+                    format!("({})", day_tasks.count)
+                };
+                format!(
+                    "{}{}\x1b[0m{}",
+                    importance_color(day_tasks.max_importance),
+                    count,
+                    " ".repeat((CELL_WIDTH - 3).saturating_sub(count.len()))
+                )
+            }
+            _ => " ".repeat(CELL_WIDTH - 3),
+        };
+        line += &number;
+        line += &count;
+
+        if date.weekday() == chrono::Weekday::Sun {
+            lines.push(line.trim_end().to_string());
+            line = String::new();
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_year_month() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        assert_eq!(parse_year_month("2026-11", today), Ok(Some((2026, 11))));
+        assert_eq!(parse_year_month("Dec", today), Ok(Some((2026, 12))));
+        assert_eq!(parse_year_month("january", today), Ok(Some((2026, 1))));
+        assert_eq!(parse_year_month("2026-13", today), Ok(None));
+        assert_eq!(parse_year_month("2026-11-03", today), Ok(None));
+        assert_eq!(parse_year_month("tomorrow", today), Ok(None));
+        assert!(parse_year_month("300000-01", today).is_err());
+
+        let max = NaiveDate::MAX.year();
+        assert_eq!(
+            parse_year_month(&format!("{}-12", max), today),
+            Ok(Some((max, 12)))
+        );
+        assert!(parse_year_month(&format!("{}-01", max + 1), today).is_err());
+        assert_eq!(days_in_month(max, 12), 31, "The last supported month");
+        assert_eq!(days_in_month(2028, 2), 29);
+    }
+
+    #[test]
+    fn test_render() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let tasks = HashMap::from([
+            (
+                3,
+                DayTasks {
+                    count: 2,
+                    max_importance: 3,
+                },
+            ),
+            (
+                4,
+                DayTasks {
+                    count: 100,
+                    max_importance: 0,
+                },
+            ),
+        ]);
+        let lines = render(2026, 11, &tasks, today);
+
+        // november 2026 starts on a sunday and has 30 days
+        assert_eq!(lines[2].trim(), "1");
+        assert!(lines[3].starts_with("  2      3\x1b[1;37;31m(2)\x1b[0m   4"));
+        assert!(lines[3].contains("99+\x1b[0m   5"));
+        assert_eq!(lines.last().unwrap().trim(), "30");
+        assert_eq!(lines.len(), 8);
+    }
+}
@@ -1,12 +1,48 @@
 //! The command trait and commands structs.
 
-use crate::interface::{add_prompt, edit_prompt, get_input};
-use crate::task::TaskList;
+use chrono::{Datelike, Local};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::agenda;
+use crate::args::Args;
+use crate::calendar;
+use crate::date;
+use crate::filter::Filter;
+use crate::formats;
+use crate::interface::{
+    add_prompt, apply_args, edit_prompt, get_input, has_task_flags, task_from_args, terminal_width,
+    TASK_FLAGS,
+};
+use crate::matrix;
+use crate::output::{self, with_field, Output};
+use crate::search;
+use crate::task::{format_count, format_path, is_selector, TaskList};
+use crate::tree;
 
 pub trait Command {
     fn keywords(&self) -> &[&str];
-    fn execute(&self, arg: &str, task_list: &mut TaskList) -> Result<String, String>;
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String>;
     fn help(&self) -> &str;
+
+    /// The `--flags` the command takes, any other flag is an error.
+    fn flags(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Whether the changes made by the command can be undone.
+    fn journaled(&self) -> bool {
+        true
+    }
+}
+
+/// Resolves the task id or path from the argument or asks for it if there is none.
+fn get_path(arg: &str, task_list: &TaskList) -> Result<Vec<usize>, String> {
+    if !arg.is_empty() {
+        task_list.resolve(arg)
+    } else {
+        task_list.resolve(&get_input!("Task (id or path): ", ""))
+    }
 }
 
 struct AddCommand;
@@ -15,31 +51,46 @@ impl Command for AddCommand {
         &["add", "a"]
     }
 
-    fn execute(&self, arg: &str, task_list: &mut TaskList) -> Result<String, String> {
-        if arg == "" {
-            if let Some(last_shown) = task_list.last_shown {
-                let last_task = &mut task_list.tasks[last_shown - 1];
-                println!("Adding sub-task to: {}", last_task.title);
-                last_task.add_sub_task(add_prompt());
-            } else {
-                task_list.add_task(add_prompt());
-            }
+    fn flags(&self) -> &[&str] {
+        TASK_FLAGS
+    }
+
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        // the first argument is the parent id or path if it's valid, the rest is the title
+        let (parent, title) = if is_selector(args.arg()) {
+            (
+                task_list.resolve(args.arg())?,
+                args.positional[1..].join(" "),
+            )
         } else {
-            let ind: usize = arg.parse().unwrap_or(0);
-            if ind < 1 || ind > task_list.tasks.len() {
-                return Err(format!(
-                    "Task index must be a number between 1 and {}!",
-                    task_list.tasks.len()
-                ));
+            (Vec::new(), args.positional.join(" "))
+        };
+        let title = if title.is_empty() {
+            args.get(&["title"]).unwrap_or("").to_string()
+        } else {
+            title
+        };
+
+        // ask for the task only if nothing was passed as arguments
+        let task = if title.is_empty() && !has_task_flags(args) {
+            if !parent.is_empty() {
+                let parent_task = task_list.get(&parent)?;
+                eprintln!("Adding sub-task to: {}", parent_task.title);
             }
-            task_list.tasks[ind - 1].add_sub_task(add_prompt());
-        }
+            add_prompt()
+        } else {
+            task_from_args(title, args)?
+        };
+
+        let id = task_list.insert(&parent, task)?;
+        let path = task_list.find_id(id).unwrap_or_default();
+        let task = task_list.get(&path)?;
 
-        Ok("Task added".into())
+        Ok(Output::message(format!("Task #{} added", id)).with_data(task.to_json(&path, false)))
     }
 
     fn help(&self) -> &str {
-        "add [index] - adds a new task. If index is specified, it will add a sub task to the task at the specified index."
+        "add [id|path] [title] [--desc text] [--importance n] [--due date] [--tags \"+tag\"] [--repeat rule] - adds a new task. Words starting with + in the title are added as tags. If an id (e.g. #12) or a path (e.g. 3 or 3.2) is specified, it will add a sub task to that task. If no title or flags are given, the task is entered interactively."
     }
 }
 
@@ -49,27 +100,24 @@ impl Command for EditCommand {
         &["edit", "e"]
     }
 
-    fn execute(&self, arg: &str, task_list: &mut TaskList) -> Result<String, String> {
-        let task_id: usize = if arg != "" {
-            arg.parse().unwrap_or(0)
-        } else {
-            get_input!("Task ID: ", "").parse().unwrap_or(0)
-        };
+    fn flags(&self) -> &[&str] {
+        TASK_FLAGS
+    }
 
-        if task_id > 0 && task_id <= task_list.tasks.len() {
-            let task = edit_prompt(&task_list.tasks[task_id - 1]);
-            task_list.tasks[task_id - 1] = task;
-        } else if task_id > task_list.tasks.len() {
-            return Err(format!("Last id is {}!", task_list.tasks.len()));
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let path = get_path(args.arg(), task_list)?;
+        let task = task_list.get_mut(&path)?;
+        if has_task_flags(args) {
+            apply_args(task, args)?;
         } else {
-            return Err("Task ID must be a positive number!".into());
+            *task = edit_prompt(task);
         }
 
-        Ok("Task edited".into())
+        Ok(Output::message("Task edited").with_data(task.to_json(&path, false)))
     }
 
     fn help(&self) -> &str {
-        "edit [index] - edits the task at the specified index. If no index is specified, it will edit the last shown task."
+        "edit [id|path] [--title text] [--desc text] [--importance n] [--due date] [--tags \"+tag -tag\"] [--repeat rule] - edits the specified task. If no flags are given, the task is edited interactively."
     }
 }
 
@@ -79,71 +127,342 @@ impl Command for ShowCommand {
         &["show", "s"]
     }
 
-    fn execute(&self, arg: &str, task_list: &mut TaskList) -> Result<String, String> {
+    fn flags(&self) -> &[&str] {
+        &["tree", "depth"]
+    }
+
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let arg = args.arg();
         if task_list.tasks.is_empty() {
             return Err("No tasks to show!".into());
         }
-        if arg != "" {
-            let ind = arg.parse().unwrap_or(0);
-            if let Some(last_shown) = task_list.last_shown {
-                let task = &task_list.tasks[last_shown - 1];
-                return if ind > 0 && ind <= task.sub_tasks.len() {
-                    task.sub_tasks[ind - 1].print_task();
-                    Ok("".into())
-                } else if ind > task.sub_tasks.len() {
-                    Err(format!("Last id is {}!", task.sub_tasks.len()))
-                } else {
-                    Err("Task ID must be a positive number!".into())
-                };
-            }
-
-            return if ind > 0 && ind <= task_list.tasks.len() {
-                task_list.tasks[ind - 1].print_task();
-                task_list.last_shown = Some(ind);
-                Ok("".into())
-            } else if ind > task_list.tasks.len() {
-                Err(format!("Last id is {}!", task_list.tasks.len()))
+        if args.has(&["tree", "depth"]) {
+            let max_depth = match args.get(&["depth"]) {
+                Some(depth) => match depth.parse::<usize>() {
+                    Ok(depth) if depth > 0 => Some(depth),
+                    _ => return Err("Depth must be a positive number!".into()),
+                },
+                None => None,
+            };
+            let roots = if arg.is_empty() {
+                task_list.pending_roots()
             } else {
-                Err("Task ID must be a positive number!".into())
+                let path = task_list.resolve(arg)?;
+                vec![(path.clone(), task_list.get(&path)?)]
             };
+            let text = tree::render(&roots, max_depth, terminal_width())
+                .iter()
+                .map(|line| format!("{}\n", line))
+                .collect();
+            let data = roots
+                .iter()
+                .map(|(path, task)| {
+                    let (done, total) = tree::progress(task);
+                    let progress = json!({ "done": done, "total": total });
+                    with_field(task.to_json(path, true), "progress", progress)
+                })
+                .collect();
+            Ok(Output::new(text, data))
+        } else if arg == "done" && args.positional.len() == 1 {
+            Ok(output::task_list(&task_list.done_tasks(), false))
+        } else if is_selector(arg) && args.positional.len() == 1 {
+            let path = task_list.resolve(arg)?;
+            let task = task_list.get(&path)?;
+            Ok(Output::new(
+                task.details(&format_path(&path)),
+                task.to_json(&path, true),
+            ))
+        } else if !arg.is_empty() {
+            let filter = Filter::parse(&args.positional.join(" "))?;
+            // done tasks are only shown if the filter asks about them
+            let show_done = filter.mentions_done();
+            let tasks =
+                task_list.matching(|task| (show_done || !task.is_done()) && filter.matches(task));
+            Ok(output::task_list(&tasks, false))
         } else {
-            task_list.last_shown = None;
-            task_list.print_tasks();
-            return Ok("".into());
+            Ok(output::task_list(&task_list.pending_roots(), true))
         }
     }
 
     fn help(&self) -> &str {
-        "show [index] - shows the task at the specified index. If no index is specified, it will show all the tasks."
+        "show [id|path|done|filter] [--tree] [--depth n] - shows the task with the specified id (e.g. #12) or at the specified path (e.g. 3 or 3.2.1). If no task is specified, it will show all the tasks that are not done. 'show done' lists the done tasks. A filter lists the matching tasks and sub-tasks, e.g. 'show importance>=2 and due<1w and +work'. Filters can use +tag, -tag, done, overdue, recurring, due, comparisons of importance, due, created, completed, title and desc (=, !=, <, <=, >, >=, ~ for contains), and, or, not and parentheses. --tree shows the whole hierarchy (or the one of the specified task) as a tree with the progress of each task, --depth limits how deep it goes."
     }
 }
 
-struct InfoCommand;
-impl Command for InfoCommand {
+struct NextCommand;
+impl Command for NextCommand {
     fn keywords(&self) -> &[&str] {
-        &["info", "i"]
+        &["next", "n"]
+    }
+
+    fn execute(&self, _args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let (path, task) = task_list
+            .most_urgent()
+            .ok_or_else(|| "There is nothing to do!".to_string())?;
+        let urgency = task.urgency(&task_list.config.urgency);
+        Ok(Output::new(
+            format!(
+                "{}: {}",
+                format_path(&path),
+                task.details(&format_path(&path))
+            ),
+            with_field(task.to_json(&path, true), "urgency", urgency),
+        )
+        .with_message(format!("Urgency: {:.1}", urgency)))
+    }
+
+    fn help(&self) -> &str {
+        "next - shows the most urgent task that can be worked on (one without sub-tasks left to do). Urgency combines importance, time left, age, sub-tasks and tags, and can be configured in the config file."
+    }
+}
+
+struct AgendaCommand;
+impl Command for AgendaCommand {
+    fn keywords(&self) -> &[&str] {
+        &["agenda"]
     }
 
-    fn execute(&self, arg: &str, task_list: &mut TaskList) -> Result<String, String> {
-        let ind: usize = if arg != "" {
-            arg.parse().unwrap_or(0)
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let range = args.positional.join(" ");
+        let end = if range.is_empty() {
+            None
         } else {
-            get_input!("Task ID: ", "").parse().unwrap_or(0)
+            Some(date::parse_date(&range).map_err(|e| e.to_string())?)
         };
 
-        return if ind > 0 && ind <= task_list.tasks.len() {
-            task_list.tasks[ind - 1].print_info();
-            task_list.last_shown = Some(ind);
-            Ok("".into())
-        } else if ind > task_list.tasks.len() {
-            Err(format!("Last id is {}!", task_list.tasks.len()))
+        let tasks = task_list
+            .flatten()
+            .into_iter()
+            .filter(|(_, task)| !task.is_done())
+            .filter_map(|(path, task)| Some((task.due_date?, (path, task))))
+            .collect();
+        let groups = agenda::group(tasks, Local::now(), end);
+        if groups.is_empty() {
+            return Err("No tasks are due!".into());
+        }
+
+        let mut text = String::new();
+        let mut data = Vec::new();
+        for (group, tasks) in groups {
+            text += &format!("\x1b[1;4;37m{}\x1b[0m:\n", group.heading());
+            for (due, (path, task)) in tasks {
+                text += &format!(
+                    "  \x1b[2;37;37m{}\x1b[0m  {}: {}\n",
+                    group.format_due(due),
+                    format_path(&path),
+                    task.header(&[])
+                );
+                data.push(with_field(
+                    task.to_json(&path, false),
+                    "group",
+                    group.heading(),
+                ));
+            }
+        }
+        Ok(Output::new(text, Value::Array(data)))
+    }
+
+    fn help(&self) -> &str {
+        "agenda [range] - lists the tasks (and sub-tasks) that are due, grouped by day: overdue, today, tomorrow, the days of the coming week and later. The range limits it to tasks due until a date, e.g. 'agenda 1w', 'agenda eom' or 'agenda friday'."
+    }
+}
+
+struct CalendarCommand;
+impl Command for CalendarCommand {
+    fn keywords(&self) -> &[&str] {
+        &["calendar", "cal"]
+    }
+
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let arg = args.positional.join(" ");
+        let today = Local::now().naive_local().date();
+        let pending: Vec<_> = task_list
+            .flatten()
+            .into_iter()
+            .filter(|(_, task)| !task.is_done())
+            .filter_map(|(path, task)| Some((task.due_date?.naive_local(), path, task)))
+            .collect();
+
+        let month = if arg.is_empty() {
+            Some((today.year(), today.month()))
         } else {
-            Err("Task ID must be a positive number!".into())
+            calendar::parse_year_month(&arg, today)?
+        };
+        if let Some((year, month)) = month {
+            let mut days: HashMap<u32, calendar::DayTasks> = HashMap::new();
+            for (due, _, task) in pending.iter() {
+                if due.year() == year && due.month() == month {
+                    let day = days.entry(due.day()).or_default();
+                    day.count += 1;
+                    day.max_importance = day.max_importance.max(task.importance);
+                }
+            }
+            let text = calendar::render(year, month, &days, today)
+                .iter()
+                .map(|line| format!("{}\n", line))
+                .collect();
+            let data = pending
+                .iter()
+                .filter(|(due, _, _)| due.year() == year && due.month() == month)
+                .map(|(_, path, task)| task.to_json(path, false))
+                .collect();
+            return Ok(Output::new(text, data));
+        }
+
+        // a single day
+        let day = date::parse_date(&arg)
+            .map_err(|e| e.to_string())?
+            .naive_local()
+            .date();
+        let mut tasks: Vec<_> = pending
+            .iter()
+            .filter(|(due, _, _)| due.date() == day)
+            .collect();
+        if tasks.is_empty() {
+            return Err(format!("No tasks are due on {}", day.format("%Y-%m-%d")));
+        }
+        tasks.sort_by_key(|(due, _, _)| *due);
+        let mut text = format!("\x1b[1;4;37m{}\x1b[0m:\n", day.format("%A %Y-%m-%d"));
+        for (due, path, task) in tasks.iter() {
+            text += &format!(
+                "  \x1b[2;37;37m{}\x1b[0m  {}: {}\n",
+                due.format("%H:%M"),
+                format_path(path),
+                task.header(&[])
+            );
+        }
+        let data = tasks
+            .iter()
+            .map(|(_, path, task)| task.to_json(path, false))
+            .collect();
+        Ok(Output::new(text, data))
+    }
+
+    fn help(&self) -> &str {
+        "calendar [month|day] - shows a month (e.g. 2026-11 or nov, the current one by default) with the number of tasks due on each day, colored by the most important one. With a day (e.g. 2026-11-03 or tomorrow) it lists the tasks due that day."
+    }
+}
+
+struct MatrixCommand;
+impl Command for MatrixCommand {
+    fn keywords(&self) -> &[&str] {
+        &["matrix", "m"]
+    }
+
+    fn execute(&self, _args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let mut quadrants = matrix::classify(task_list.flatten(), &task_list.config.matrix);
+        let weights = &task_list.config.urgency;
+        for tasks in quadrants.iter_mut() {
+            tasks.sort_by(|(_, a), (_, b)| b.urgency(weights).total_cmp(&a.urgency(weights)));
+        }
+        let text = matrix::render(&quadrants, terminal_width())
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let data = matrix::Quadrant::ALL
+            .iter()
+            .zip(quadrants.iter())
+            .flat_map(|(quadrant, tasks)| {
+                tasks.iter().map(|(path, task)| {
+                    with_field(task.to_json(path, false), "quadrant", quadrant.name())
+                })
+            })
+            .collect();
+        Ok(Output::new(text, data))
+    }
+
+    fn help(&self) -> &str {
+        "matrix - shows the tasks that aren't done in an Eisenhower matrix: split by whether they are important and urgent. The thresholds can be configured in the config file."
+    }
+}
+
+struct SearchCommand;
+impl Command for SearchCommand {
+    fn keywords(&self) -> &[&str] {
+        &["search", "find"]
+    }
+
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let query = args.positional.join(" ");
+        if query.trim().is_empty() {
+            return Err("Nothing to search for!".into());
+        }
+        let hits = search::search(task_list, &query);
+        if hits.is_empty() {
+            return Err(format!("No tasks match '{}'", query));
+        }
+
+        let mut text = String::new();
+        for hit in hits.iter() {
+            text += &format!(
+                "{}: {}\n",
+                format_path(&hit.path),
+                hit.task.header(&hit.title_positions)
+            );
+            if !hit.description_positions.is_empty() {
+                let desc = hit.task.description.as_deref().unwrap_or_default();
+                text += &format!(
+                    "    \x1b[2m{}\x1b[0m\n",
+                    search::highlight(desc, &hit.description_positions)
+                );
+            }
+        }
+        let data = hits
+            .iter()
+            .map(|hit| with_field(hit.task.to_json(&hit.path, false), "score", hit.score))
+            .collect();
+        Ok(Output::new(text, data).with_message(format!("Tasks found: {}", hits.len())))
+    }
+
+    fn help(&self) -> &str {
+        "search <text> - finds tasks (and sub-tasks) whose title or description fuzzy matches the text, best matches first. Prints their paths and ids for use with other commands."
+    }
+}
+
+struct RandomCommand;
+impl Command for RandomCommand {
+    fn keywords(&self) -> &[&str] {
+        &["random", "rand"]
+    }
+
+    fn execute(&self, _args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let Some(task) = task_list.random_task() else {
+            return Ok(Output::message("You have no tasks!"));
         };
+        let path = task_list.find_id(task.id).unwrap_or_default();
+        Ok(Output::new(
+            format!("Random Task:\n{}", task.details(&format_path(&path))),
+            task.to_json(&path, true),
+        ))
     }
 
     fn help(&self) -> &str {
-        "info [index] - shows all stored information about the task at the specified index."
+        "random - shows a random task that isn't done."
+    }
+}
+
+struct InfoCommand;
+impl Command for InfoCommand {
+    fn keywords(&self) -> &[&str] {
+        &["info", "i"]
+    }
+
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let path = get_path(args.arg(), task_list)?;
+        let task = task_list.get(&path)?;
+        let urgency = task.urgency(&task_list.config.urgency);
+        Ok(Output::new(
+            format!(
+                "{}\x1b[1;37;37mUrgency:\x1b[0m {:.1}\n",
+                task.info(),
+                urgency
+            ),
+            with_field(task.to_json(&path, true), "urgency", urgency),
+        ))
+    }
+
+    fn help(&self) -> &str {
+        "info [id|path] - shows all stored information about the specified task."
     }
 }
 
@@ -153,32 +472,93 @@ impl Command for RemoveCommand {
         &["remove", "r"]
     }
 
-    fn execute(&self, arg: &str, task_list: &mut TaskList) -> Result<String, String> {
-        let ind: usize = if arg != "" {
-            arg.parse().unwrap_or(0)
-        } else {
-            get_input!("Task ID: ", "").parse().unwrap_or(0)
-        };
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let path = get_path(args.arg(), task_list)?;
+        let removed = task_list.remove(&path)?;
+        Ok(Output::message(format!("Task '{}' removed", removed.title))
+            .with_data(removed.to_json(&path, true)))
+    }
+
+    fn help(&self) -> &str {
+        "remove [id|path] - removes the specified task and all its sub-tasks."
+    }
+}
+
+struct DoneCommand;
+impl Command for DoneCommand {
+    fn keywords(&self) -> &[&str] {
+        &["done", "d"]
+    }
 
-        let mut task_list_to_mod = &mut task_list.tasks;
-        if let Some(last_shown) = task_list.last_shown {
-            if ind > 0 && ind <= task_list_to_mod[last_shown - 1].sub_tasks.len() {
-                task_list_to_mod = &mut task_list_to_mod[last_shown - 1].sub_tasks;
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let path = get_path(args.arg(), task_list)?;
+        let task = task_list.get_mut(&path)?;
+        if task.is_done() {
+            return Err(format!("Task '{}' is already done!", task.title));
+        }
+        task.date_completed = Some(Local::now());
+
+        let title = task.title.clone();
+        let next = match task.next_occurrence() {
+            Ok(next) => next,
+            // the task can still be done, it just doesn't repeat anymore
+            Err(e) => {
+                task.recurrence = None;
+                return Ok(Output::message(format!(
+                    "Task '{}' marked as done, it doesn't repeat anymore: {}",
+                    title, e
+                ))
+                .with_data(task.to_json(&path, false)));
             }
+        };
+        if let Some(next) = next {
+            // the next occurrence carries on the recurrence
+            task.recurrence = None;
+            let due = next.due_date.unwrap();
+            let data = task.to_json(&path, false);
+            let id = task_list.insert_after(&path, next)?;
+            let next_path = task_list.find_id(id).unwrap_or_default();
+            let next = task_list.get(&next_path)?.to_json(&next_path, false);
+            return Ok(Output::message(format!(
+                "Task '{}' marked as done, next one (#{}) is due {}",
+                title,
+                id,
+                due.format("%Y-%m-%d %H:%M")
+            ))
+            .with_data(with_field(data, "next", next)));
         }
 
-        return if ind > 0 && ind <= task_list_to_mod.len() {
-            let removed = task_list_to_mod.remove(ind - 1);
-            Ok(format!("Task '{}' removed", removed.title))
-        } else if ind > task_list_to_mod.len() {
-            Err(format!("Last id is {}!", task_list_to_mod.len()))
-        } else {
-            Err("Task ID must be a positive number!".into())
-        };
+        Ok(Output::message(format!("Task '{}' marked as done", title))
+            .with_data(task.to_json(&path, false)))
+    }
+
+    fn help(&self) -> &str {
+        "done [id|path] - marks the specified task as done. Done tasks are hidden from the task list. A recurring task is added again with the next due date."
+    }
+}
+
+struct UndoneCommand;
+impl Command for UndoneCommand {
+    fn keywords(&self) -> &[&str] {
+        &["undone"]
+    }
+
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let path = get_path(args.arg(), task_list)?;
+        let task = task_list.get_mut(&path)?;
+        if !task.is_done() {
+            return Err(format!("Task '{}' is not done!", task.title));
+        }
+        task.date_completed = None;
+
+        Ok(
+            Output::message(format!("Task '{}' marked as not done", task.title))
+                .with_data(task.to_json(&path, false)),
+        )
     }
 
     fn help(&self) -> &str {
-        "remove [index] - removes the task at the specified index."
+        "undone [id|path] - marks the specified task as not done."
     }
 }
 
@@ -188,12 +568,12 @@ impl Command for SortCommand {
         &["sort"]
     }
 
-    fn execute(&self, arg: &str, task_list: &mut TaskList) -> Result<String, String> {
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let arg = args.arg();
         let mut msg = "Sorted by creation date".to_string();
-        if arg == "" {
+        if arg.is_empty() {
             task_list.sort_by_date_created();
-            task_list.print_tasks();
-            Ok(msg)
+            Ok(output::task_list(&task_list.pending_roots(), true).with_message(msg))
         } else {
             match arg {
                 "due" | "d" => {
@@ -207,17 +587,206 @@ impl Command for SortCommand {
                 "created" | "c" => {
                     task_list.sort_by_date_created();
                 }
+                "urgency" | "u" => {
+                    task_list.sort_by_urgency();
+                    msg = "Sorted by urgency.".into();
+                }
                 _ => {
                     return Err("Invalid sort type!".into());
                 }
             }
-            task_list.print_tasks();
-            Ok(msg)
+            Ok(output::task_list(&task_list.pending_roots(), true).with_message(msg))
         }
     }
 
     fn help(&self) -> &str {
-        "sort [type] - sorts all tasks by the specified criteria. Type can be: created, due, importance. If no type is specified, it will sort by created."
+        "sort [type] - sorts all tasks by the specified criteria. Type can be: created, due, importance, urgency. If no type is specified, it will sort by created."
+    }
+}
+
+struct UndoCommand;
+impl Command for UndoCommand {
+    fn keywords(&self) -> &[&str] {
+        &["undo", "u"]
+    }
+
+    fn execute(&self, _args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let entry = task_list.journal.undo().ok_or("Nothing to undo!")?;
+        task_list.restore(entry.revert(&task_list.snapshot()));
+        Ok(Output::message(format!("Undid '{}'", entry.command))
+            .with_data(json!({ "command": entry.command })))
+    }
+
+    fn help(&self) -> &str {
+        "undo - reverts the last change to the tasks."
+    }
+
+    fn journaled(&self) -> bool {
+        false
+    }
+}
+
+struct RedoCommand;
+impl Command for RedoCommand {
+    fn keywords(&self) -> &[&str] {
+        &["redo"]
+    }
+
+    fn execute(&self, _args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let entry = task_list.journal.redo().ok_or("Nothing to redo!")?;
+        task_list.restore(entry.apply(&task_list.snapshot()));
+        Ok(Output::message(format!("Redid '{}'", entry.command))
+            .with_data(json!({ "command": entry.command })))
+    }
+
+    fn help(&self) -> &str {
+        "redo - applies the last undone change again."
+    }
+
+    fn journaled(&self) -> bool {
+        false
+    }
+}
+
+struct RestoreCommand;
+impl Command for RestoreCommand {
+    fn keywords(&self) -> &[&str] {
+        &["restore"]
+    }
+
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let backups = task_list.storage().backups();
+        if backups.is_empty() {
+            return Err("There are no backups!".into());
+        }
+
+        let arg = args.arg();
+        if arg.is_empty() {
+            let mut text = String::new();
+            let mut data = Vec::new();
+            for (i, backup) in backups.iter().enumerate() {
+                let document = task_list.storage().load_backup(backup);
+                let count = match &document {
+                    Ok(document) => format_count(document.tasks.len()),
+                    Err(e) => e.clone(),
+                };
+                text += &format!(
+                    "{}: {} \x1b[2;37;37m({})\x1b[0m\n",
+                    i + 1,
+                    backup.date.format("%Y-%m-%d %H:%M:%S"),
+                    count
+                );
+                let mut entry = json!({ "index": i + 1, "date": backup.date.to_rfc3339() });
+                entry = match document {
+                    Ok(document) => with_field(entry, "tasks", document.tasks.len()),
+                    Err(e) => with_field(entry, "error", e),
+                };
+                data.push(entry);
+            }
+            return Ok(Output::new(text, Value::Array(data)));
+        }
+
+        let ind: usize = arg.parse().unwrap_or(0);
+        let backup = if ind > 0 && ind <= backups.len() {
+            &backups[ind - 1]
+        } else {
+            return Err(format!(
+                "Backup index must be a number between 1 and {}!",
+                backups.len()
+            ));
+        };
+        let document = task_list.storage().load_backup(backup)?;
+        task_list.restore(document.tasks);
+
+        Ok(Output::message(format!(
+            "Restored backup from {}",
+            backup.date.format("%Y-%m-%d %H:%M:%S")
+        )))
+    }
+
+    fn help(&self) -> &str {
+        "restore [index] - lists the backups of the save file. If index is specified, it will replace all tasks with the ones from that backup (this can be undone)."
+    }
+}
+
+struct ImportCommand;
+impl Command for ImportCommand {
+    fn keywords(&self) -> &[&str] {
+        &["import"]
+    }
+
+    fn flags(&self) -> &[&str] {
+        &["delimiter"]
+    }
+
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let (name, file) = match args.positional.as_slice() {
+            [name, file] => (name, file),
+            _ => return Err("Usage: import <format> <file>".into()),
+        };
+        let format = formats::find(name, args)?;
+        let input =
+            std::fs::read_to_string(file).map_err(|e| format!("Could not read {}: {}", file, e))?;
+        let import = format.import(&input)?;
+
+        let count = formats::count(&import.tasks);
+        let mut data = Vec::new();
+        for task in import.tasks {
+            let id = task_list.insert(&[], task)?;
+            let path = task_list.find_id(id).unwrap_or_default();
+            data.push(task_list.get(&path)?.to_json(&path, true));
+        }
+        let text = import
+            .warnings
+            .iter()
+            .map(|warning| format!("{}\n", warning))
+            .collect();
+        Ok(
+            Output::new(text, json!({ "tasks": data, "warnings": import.warnings }))
+                .with_message(format!("Imported {} from {}", format_count(count), file)),
+        )
+    }
+
+    fn help(&self) -> &str {
+        "import <format> <file> [--delimiter c] - adds the tasks from a file of another todo app. Formats: todotxt, ics, md, csv (--delimiter sets the separator, e.g. ; or tab), taskwarrior (the JSON of `task export`). Anything that couldn't be imported is listed. The import can be undone."
+    }
+}
+
+struct ExportCommand;
+impl Command for ExportCommand {
+    fn keywords(&self) -> &[&str] {
+        &["export"]
+    }
+
+    fn flags(&self) -> &[&str] {
+        &["delimiter"]
+    }
+
+    fn execute(&self, args: &Args, task_list: &mut TaskList) -> Result<Output, String> {
+        let (name, file) = match args.positional.as_slice() {
+            [name] => (name, None),
+            [name, file] => (name, Some(file)),
+            _ => return Err("Usage: export <format> [file]".into()),
+        };
+        let format = formats::find(name, args)?;
+        let exported = format.export(&task_list.tasks)?;
+        let count = formats::count(&task_list.tasks);
+
+        match file {
+            Some(file) => {
+                std::fs::write(file, &exported)
+                    .map_err(|e| format!("Could not write {}: {}", file, e))?;
+                Ok(
+                    Output::message(format!("Exported {} to {}", format_count(count), file))
+                        .with_data(json!({ "file": file, "tasks": count })),
+                )
+            }
+            None => Ok(Output::new(exported.clone(), Value::String(exported))),
+        }
+    }
+
+    fn help(&self) -> &str {
+        "export <format> [file] [--delimiter c] - writes all tasks (including done ones and sub-tasks) in the format of another todo app to the file, or prints them. Formats: todotxt, ics, md, csv (--delimiter sets the separator, e.g. ; or tab)."
     }
 }
 
@@ -227,7 +796,7 @@ impl Command for QuitCommand {
         &["quit", "exit", "q"]
     }
 
-    fn execute(&self, _arg: &str, _task_list: &mut TaskList) -> Result<String, String> {
+    fn execute(&self, _args: &Args, _task_list: &mut TaskList) -> Result<Output, String> {
         println!("Good luck with your tasks ;)");
         std::process::exit(0);
     }
@@ -247,9 +816,22 @@ impl Commands {
             Box::new(AddCommand),
             Box::new(EditCommand),
             Box::new(ShowCommand),
+            Box::new(NextCommand),
+            Box::new(AgendaCommand),
+            Box::new(CalendarCommand),
+            Box::new(MatrixCommand),
+            Box::new(SearchCommand),
+            Box::new(RandomCommand),
             Box::new(InfoCommand),
             Box::new(RemoveCommand),
+            Box::new(DoneCommand),
+            Box::new(UndoneCommand),
             Box::new(SortCommand),
+            Box::new(UndoCommand),
+            Box::new(RedoCommand),
+            Box::new(RestoreCommand),
+            Box::new(ImportCommand),
+            Box::new(ExportCommand),
             Box::new(QuitCommand),
         ];
 
@@ -257,6 +839,11 @@ impl Commands {
     }
 }
 
+/// Returns the command that has the specified keyword.
+pub fn find_command(keyword: &str) -> Option<Box<dyn Command>> {
+    Commands::new().find(|c| c.keywords().contains(&keyword))
+}
+
 impl Iterator for Commands {
     type Item = Box<dyn Command>;
 
@@ -264,3 +851,27 @@ impl Iterator for Commands {
         self.commands.pop()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_command() {
+        let commands = Commands::new().commands;
+        let keywords: Vec<&str> = commands
+            .iter()
+            .flat_map(|c| c.keywords().iter().copied())
+            .collect();
+        for (i, keyword) in keywords.iter().enumerate() {
+            assert!(
+                !keywords[i + 1..].contains(keyword),
+                "'{}' belongs to two commands",
+                keyword
+            );
+        }
+        assert_eq!(find_command("r").unwrap().keywords()[0], "remove");
+        assert_eq!(find_command("rand").unwrap().keywords()[0], "random");
+        assert!(find_command("x").is_none());
+    }
+}
@@ -0,0 +1,95 @@
+//! User settings read from the config file. Every setting is optional.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Config {
+    pub urgency: UrgencyWeights,
+    pub matrix: MatrixThresholds,
+}
+
+/// How much each property of a task adds to its urgency.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct UrgencyWeights {
+    /// Per level of importance.
+    pub importance: f64,
+    /// For tasks that are overdue by a week or more. Tasks due later get less, down to a fifth.
+    pub due: f64,
+    /// For tasks that are a year old or older.
+    pub age: f64,
+    /// For tasks with 3 or more sub-tasks that aren't done.
+    pub sub_tasks: f64,
+    /// Per tag, e.g. `{"next": 15}`.
+    pub tags: BTreeMap<String, f64>,
+}
+
+impl Default for UrgencyWeights {
+    fn default() -> Self {
+        UrgencyWeights {
+            importance: 4.0,
+            due: 12.0,
+            age: 2.0,
+            sub_tasks: 1.0,
+            tags: BTreeMap::from([("next".to_string(), 15.0)]),
+        }
+    }
+}
+
+/// When tasks count as important and urgent in the `matrix` command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct MatrixThresholds {
+    /// Tasks with at least this importance are important.
+    pub importance: u8,
+    /// Tasks due within this many days (or overdue) are urgent.
+    pub due_days: f64,
+}
+
+impl Default for MatrixThresholds {
+    fn default() -> Self {
+        MatrixThresholds {
+            importance: 2,
+            due_days: 2.0,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file. A missing file gives the default config.
+    pub fn load(path: &Path) -> Config {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return Config::default(),
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            eprintln!(
+                "Could not parse config file {}, using the defaults: {}",
+                path.display(),
+                e
+            );
+            Config::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config() {
+        let config: Config =
+            serde_json::from_str(r#"{"urgency": {"due": 20, "tags": {"work": 3}}}"#).unwrap();
+        assert_eq!(config.urgency.due, 20.0);
+        assert_eq!(
+            config.urgency.importance,
+            UrgencyWeights::default().importance,
+            "Missing settings should keep their defaults"
+        );
+        assert_eq!(config.urgency.tags.get("work"), Some(&3.0));
+    }
+}
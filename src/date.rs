@@ -1,5 +1,6 @@
 //! A module for getting and parsing dates.
 use chrono::prelude::*;
+use chrono::Duration;
 
 pub type DateFormat = DateTime<Local>;
 
@@ -24,29 +25,289 @@ pub fn get_time_left(date: DateFormat) -> String {
     s
 }
 
-pub fn date_from_time(time: &str) -> Result<DateFormat, &str> {
-    let mut total_time = Local::now();
+/// The time of day of due dates given without a time, e.g. `tomorrow`.
+pub const END_OF_DAY: (u32, u32) = (23, 59);
 
-    for t in time.split(' ') {
-        let num = t[0..t.len() - 1].parse::<i64>();
-        let num = if let Ok(n) = num {
-            n
-        } else {
-            return Err("Invalid time format");
+/// Why a date couldn't be parsed. The strings are the part of the input that's wrong.
+#[derive(Debug, PartialEq)]
+pub enum DateError {
+    Empty,
+    InvalidOffset(String),
+    InvalidTime(String),
+    InvalidDate(String),
+    Unrecognized(String),
+}
+
+impl std::fmt::Display for DateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DateError::Empty => write!(f, "No date given!"),
+            DateError::InvalidOffset(s) => {
+                write!(f, "Invalid time offset '{}'! (use e.g. 2d 3h, units: w d h m s)", s)
+            }
+            DateError::InvalidTime(s) => {
+                write!(f, "Invalid time '{}'! (use e.g. 9am, 9:30pm or 17:00)", s)
+            }
+            DateError::InvalidDate(s) => write!(f, "Invalid date '{}'!", s),
+            DateError::Unrecognized(s) => write!(
+                f,
+                "Unrecognized date '{}'! (use e.g. tomorrow 9am, next friday, dec 24, 2026-11-03, in 3 days or 2d 3h)",
+                s
+            ),
+        }
+    }
+}
+
+/// Returns the duration of `num` units of `unit_seconds` seconds,
+/// or None if it's longer than about 10000 years.
+fn offset(num: i64, unit_seconds: i64) -> Option<Duration> {
+    const MAX_SECONDS: u64 = 10_000 * 366 * 24 * 60 * 60;
+    num.checked_mul(unit_seconds)
+        .filter(|seconds| seconds.unsigned_abs() <= MAX_SECONDS)
+        .map(Duration::seconds)
+}
+
+/// Adds time offsets like `2d 3h` (units: w d h m s) to `now`.
+pub fn date_from_time(time: &str, now: DateFormat) -> Result<DateFormat, DateError> {
+    let mut total_time = now;
+
+    for t in time.split_whitespace() {
+        let invalid = || DateError::InvalidOffset(t.to_string());
+        let unit = t.chars().last().ok_or_else(invalid)?;
+        let num = t[..t.len() - unit.len_utf8()]
+            .parse::<i64>()
+            .map_err(|_| invalid())?;
+
+        let unit_seconds = match unit {
+            'w' => 7 * 24 * 60 * 60,
+            'd' => 24 * 60 * 60,
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
         };
+        total_time = offset(num, unit_seconds)
+            .and_then(|duration| total_time.checked_add_signed(duration))
+            .ok_or_else(invalid)?;
+    }
+    Ok(total_time)
+}
+
+/// Returns true if the word looks like a time offset, e.g. `2d` or `-5w`.
+fn is_offset(word: &str) -> bool {
+    let num = word.trim_end_matches(char::is_alphabetic);
+    num.len() < word.len() && num.trim_start_matches('-').parse::<u64>().is_ok()
+}
+
+/// Parses a due date relative to the current time. See `parse_date_at`.
+pub fn parse_date(input: &str) -> Result<DateFormat, DateError> {
+    parse_date_at(input, Local::now())
+}
 
-        let unit = t.chars().last().unwrap();
+/// Parses a date given as:
+/// - time offsets: `2d 3h`, `in 3 days`
+/// - days: `today`, `tomorrow`, `friday`, `next friday`, `eow` (sunday), `eom` (last day of the month)
+/// - dates: `2026-11-03`, `dec 24`, `24 dec`
+///
+/// Days and dates can be followed by a time (`tomorrow 9am`, `2026-11-03 14:00`),
+/// otherwise the end of the day is used. A time alone means today.
+pub fn parse_date_at(input: &str, now: DateFormat) -> Result<DateFormat, DateError> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return Err(DateError::Empty);
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(&input) {
+        return Ok(date.with_timezone(&Local));
+    }
+
+    let mut words: Vec<&str> = input.split_whitespace().collect();
+    if words.iter().all(|w| is_offset(w)) {
+        return date_from_time(&input, now);
+    }
+    if let ["in", offset @ ..] = words.as_slice() {
+        return parse_in(offset, now).ok_or_else(|| DateError::InvalidOffset(offset.join(" ")));
+    }
 
-        total_time = match unit {
-            'w' => total_time + chrono::Duration::weeks(num),
-            'd' => total_time + chrono::Duration::days(num),
-            'h' => total_time + chrono::Duration::hours(num),
-            'm' => total_time + chrono::Duration::minutes(num),
-            's' => total_time + chrono::Duration::seconds(num),
-            _ => return Err("Invalid time format"),
+    let mut time = None;
+    if let Some(&last) = words.last() {
+        if let Some(t) = parse_time(last) {
+            time = Some(t);
+            words.pop();
+            if words.last() == Some(&"at") {
+                words.pop();
+            }
+        } else if last.contains(':') || last.ends_with("am") || last.ends_with("pm") {
+            return Err(DateError::InvalidTime(last.to_string()));
         }
     }
-    Ok(total_time)
+
+    let today = now.naive_local().date();
+    let date = parse_day(&words, today)?;
+    let time =
+        time.unwrap_or_else(|| NaiveTime::from_hms_opt(END_OF_DAY.0, END_OF_DAY.1, 0).unwrap());
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .ok_or(DateError::InvalidDate(input))
+}
+
+/// Parses the part after `in`, e.g. `3 days`, `an hour` or `2w`.
+fn parse_in(words: &[&str], now: DateFormat) -> Option<DateFormat> {
+    let (num, unit) = match words {
+        [offset] => return date_from_time(offset, now).ok(),
+        ["a" | "an", unit] => (1, *unit),
+        [num, unit] => (num.parse::<i64>().ok()?, *unit),
+        _ => return None,
+    };
+    let unit_seconds = match unit.trim_end_matches('s') {
+        "min" | "minute" => 60,
+        "hour" => 60 * 60,
+        "day" => 24 * 60 * 60,
+        "week" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    now.checked_add_signed(offset(num, unit_seconds)?)
+}
+
+/// Parses a time of day like `9am`, `9:30pm`, `17:00` or `noon`.
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    match word {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    let (time, pm) = if let Some(time) = word.strip_suffix("am") {
+        (time, Some(false))
+    } else if let Some(time) = word.strip_suffix("pm") {
+        (time, Some(true))
+    } else {
+        (word, None)
+    };
+    let (hour, minute) = match time.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        None if pm.is_some() => (time.parse::<u32>().ok()?, 0),
+        _ => return None,
+    };
+    let hour = match pm {
+        Some(_) if hour == 0 || hour > 12 => return None,
+        Some(pm) => hour % 12 + if pm { 12 } else { 0 },
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Parses a lowercase weekday name like `fri` or `friday`.
+pub fn parse_weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "monday" | "mon" => Some(Weekday::Mon),
+        "tuesday" | "tue" => Some(Weekday::Tue),
+        "wednesday" | "wed" => Some(Weekday::Wed),
+        "thursday" | "thu" => Some(Weekday::Thu),
+        "friday" | "fri" => Some(Weekday::Fri),
+        "saturday" | "sat" => Some(Weekday::Sat),
+        "sunday" | "sun" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Returns the lowercase name of the weekday, e.g. `friday`.
+pub fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+/// Parses a lowercase month name like `dec`, `sept` or `december`
+/// (the full name or the start of it with at least 3 letters).
+pub fn parse_month(word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    if word.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|month| month.starts_with(word))
+        .map(|i| i as u32 + 1)
+}
+
+/// Parses a day of the month like `24` or `24th`.
+fn parse_day_of_month(word: &str) -> Option<u32> {
+    let day = word
+        .strip_suffix("st")
+        .or_else(|| word.strip_suffix("nd"))
+        .or_else(|| word.strip_suffix("rd"))
+        .or_else(|| word.strip_suffix("th"))
+        .unwrap_or(word);
+    day.parse().ok()
+}
+
+/// Returns the first day after `today` that is the weekday.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days = (weekday.num_days_from_monday() + 6 - today.weekday().num_days_from_monday()) % 7;
+    today + Duration::days(days as i64 + 1)
+}
+
+/// Parses the day part of a date.
+fn parse_day(words: &[&str], today: NaiveDate) -> Result<NaiveDate, DateError> {
+    let unrecognized = || DateError::Unrecognized(words.join(" "));
+    match *words {
+        [] | ["today"] => Ok(today),
+        ["tomorrow"] | ["tom"] => Ok(today + Duration::days(1)),
+        ["eow"] => {
+            let days = 6 - today.weekday().num_days_from_monday();
+            Ok(today + Duration::days(days as i64))
+        }
+        ["eom"] => {
+            let (year, month) = if today.month() == 12 {
+                (today.year() + 1, 1)
+            } else {
+                (today.year(), today.month() + 1)
+            };
+            Ok(NaiveDate::from_ymd_opt(year, month, 1).unwrap() - Duration::days(1))
+        }
+        [day] | ["next", day] if parse_weekday(day).is_some() => {
+            Ok(next_weekday(today, parse_weekday(day).unwrap()))
+        }
+        [date] if date.contains('-') => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| DateError::InvalidDate(date.to_string())),
+        [a, b] => {
+            let (month, day) = match (parse_month(a), parse_month(b)) {
+                (Some(month), None) => (month, b),
+                (None, Some(month)) => (month, a),
+                _ => return Err(unrecognized()),
+            };
+            let day = parse_day_of_month(day).ok_or_else(unrecognized)?;
+            let invalid = || DateError::InvalidDate(words.join(" "));
+            let date = NaiveDate::from_ymd_opt(today.year(), month, day).ok_or_else(invalid)?;
+            if date >= today {
+                Ok(date)
+            } else {
+                // dates that already passed this year are meant for the next one
+                NaiveDate::from_ymd_opt(today.year() + 1, month, day).ok_or_else(invalid)
+            }
+        }
+        _ => Err(unrecognized()),
+    }
 }
 
 #[cfg(test)]
@@ -78,7 +339,8 @@ mod tests {
     fn test_date_from_time() {
         macro_rules! test_date {
             ($time:expr, $duration:expr, $message:expr) => {
-                let diff = Local::now() + $duration - date_from_time($time).unwrap();
+                let now = Local::now();
+                let diff = now + $duration - date_from_time($time, now).unwrap();
                 assert!(
                     diff < Duration::milliseconds(1) && diff > Duration::milliseconds(-1),
                     $message
@@ -112,9 +374,88 @@ mod tests {
 
         let time_str = "2dw 3";
         assert_eq!(
-            date_from_time(time_str),
-            Err("Invalid time format"),
+            date_from_time(time_str, Local::now()),
+            Err(DateError::InvalidOffset("2dw".into())),
             "Parsing with invalid values should return an error"
         );
     }
+
+    #[test]
+    fn test_parse_date() {
+        // a wednesday
+        let now = Local.with_ymd_and_hms(2026, 10, 14, 10, 0, 0).unwrap();
+        let date = |y, m, d, h, min| Ok(Local.with_ymd_and_hms(y, m, d, h, min, 0).unwrap());
+
+        assert_eq!(parse_date_at("today", now), date(2026, 10, 14, 23, 59));
+        assert_eq!(parse_date_at("Tomorrow 9am", now), date(2026, 10, 15, 9, 0));
+        assert_eq!(
+            parse_date_at("tomorrow at 9:30pm", now),
+            date(2026, 10, 15, 21, 30)
+        );
+        assert_eq!(parse_date_at("17:00", now), date(2026, 10, 14, 17, 0));
+        assert_eq!(parse_date_at("friday", now), date(2026, 10, 16, 23, 59));
+        assert_eq!(
+            parse_date_at("next wed noon", now),
+            date(2026, 10, 21, 12, 0),
+            "Weekdays are always in the future"
+        );
+        assert_eq!(parse_date_at("eow", now), date(2026, 10, 18, 23, 59));
+        assert_eq!(parse_date_at("eom", now), date(2026, 10, 31, 23, 59));
+        assert_eq!(
+            parse_date_at("2026-11-03 14:00", now),
+            date(2026, 11, 3, 14, 0)
+        );
+        assert_eq!(parse_date_at("dec 24", now), date(2026, 12, 24, 23, 59));
+        assert_eq!(
+            parse_date_at("1st march", now),
+            date(2027, 3, 1, 23, 59),
+            "Dates that passed are next year"
+        );
+        assert_eq!(parse_date_at("in 3 days", now), Ok(now + Duration::days(3)));
+        assert_eq!(
+            parse_date_at("in an hour", now),
+            Ok(now + Duration::hours(1))
+        );
+        assert_eq!(parse_date_at("2d 3h", now), Ok(now + Duration::hours(51)));
+
+        assert_eq!(parse_date_at(" ", now), Err(DateError::Empty));
+        assert_eq!(
+            parse_date_at("tomorrow 25:00", now),
+            Err(DateError::InvalidTime("25:00".into()))
+        );
+        assert_eq!(
+            parse_date_at("2026-02-30", now),
+            Err(DateError::InvalidDate("2026-02-30".into()))
+        );
+        assert_eq!(
+            parse_date_at("feb 30", now),
+            Err(DateError::InvalidDate("feb 30".into()))
+        );
+        assert_eq!(
+            parse_date_at("someday", now),
+            Err(DateError::Unrecognized("someday".into()))
+        );
+        assert_eq!(
+            parse_date_at("sept 3", now),
+            date(2027, 9, 3, 23, 59),
+            "Months can be the start of their name"
+        );
+        for s in ["junk 5", "decide 3", "5 marching", "ju 5"] {
+            assert_eq!(
+                parse_date_at(s, now),
+                Err(DateError::Unrecognized(s.into())),
+                "Words that only start like a month aren't months"
+            );
+        }
+        assert!(parse_date_at("in 3 fortnights", now).is_err());
+        assert_eq!(
+            parse_date_at("99999999999w", now),
+            Err(DateError::InvalidOffset("99999999999w".into()))
+        );
+        assert_eq!(
+            parse_date_at("in 99999999999 days", now),
+            Err(DateError::InvalidOffset("99999999999 days".into()))
+        );
+        assert!(parse_date_at("in 9000 weeks", now).is_ok());
+    }
 }
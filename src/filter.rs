@@ -0,0 +1,368 @@
+//! A small query language for filtering tasks, e.g. `importance>=2 and due<1w and +work and not done`.
+//!
+//! Terms are `+tag`, `-tag`, the keywords `done`, `overdue`, `recurring` and `due`,
+//! or comparisons like `importance>=2`, `due<fri`, `created>-1w` or `title~milk`.
+//! Terms can be combined with `and`, `or`, `not` and parentheses; terms next to each other
+//! are combined with `and`. Values can't contain spaces.
+
+use chrono::Local;
+
+use crate::date::{self, DateFormat};
+use crate::interface::parse_importance;
+use crate::task::{parse_tag, Task};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    NotContains,
+}
+
+/// Longer operators come first so that `>=` isn't read as `>`.
+const OPS: &[(&str, Op)] = &[
+    ("!=", Op::Ne),
+    ("<=", Op::Le),
+    (">=", Op::Ge),
+    ("!~", Op::NotContains),
+    ("=", Op::Eq),
+    ("<", Op::Lt),
+    (">", Op::Gt),
+    ("~", Op::Contains),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateField {
+    Due,
+    Created,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextField {
+    Title,
+    Description,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Tag(String),
+    Done,
+    Overdue,
+    Recurring,
+    HasDue,
+    Importance(Op, u8),
+    /// A date of `None` matches tasks without that date.
+    Date(DateField, Op, Option<DateFormat>),
+    Text(TextField, Op, String),
+}
+
+impl Op {
+    fn compare<T: PartialOrd>(&self, a: T, b: T) -> bool {
+        match self {
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+            Op::Contains | Op::NotContains => false,
+        }
+    }
+}
+
+impl Filter {
+    /// Parses a filter expression.
+    pub fn parse(input: &str) -> Result<Filter, String> {
+        let tokens = tokenize(input);
+        if tokens.is_empty() {
+            return Err("Empty filter!".into());
+        }
+        let mut parser = Parser { tokens, pos: 0 };
+        let filter = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some(token) => Err(format!("Unexpected '{}' in filter!", token)),
+        }
+    }
+
+    /// Returns true if the filter says anything about tasks being done.
+    /// Filters that don't only match tasks that aren't done.
+    pub fn mentions_done(&self) -> bool {
+        match self {
+            Filter::And(a, b) | Filter::Or(a, b) => a.mentions_done() || b.mentions_done(),
+            Filter::Not(a) => a.mentions_done(),
+            Filter::Done | Filter::Date(DateField::Completed, _, _) => true,
+            _ => false,
+        }
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        match self {
+            Filter::And(a, b) => a.matches(task) && b.matches(task),
+            Filter::Or(a, b) => a.matches(task) || b.matches(task),
+            Filter::Not(a) => !a.matches(task),
+            Filter::Tag(tag) => task.tags.contains(tag),
+            Filter::Done => task.is_done(),
+            Filter::Overdue => {
+                !task.is_done() && task.due_date.is_some_and(|due| due < Local::now())
+            }
+            Filter::Recurring => task.recurrence.is_some(),
+            Filter::HasDue => task.due_date.is_some(),
+            Filter::Importance(op, importance) => op.compare(task.importance, *importance),
+            Filter::Date(field, op, date) => {
+                let value = match field {
+                    DateField::Due => task.due_date,
+                    DateField::Created => Some(task.date_created),
+                    DateField::Completed => task.date_completed,
+                };
+                match (value, date) {
+                    // dates are equal if they are on the same day
+                    (Some(value), Some(date)) if matches!(op, Op::Eq | Op::Ne) => {
+                        op.compare(value.naive_local().date(), date.naive_local().date())
+                    }
+                    (Some(value), Some(date)) => op.compare(value, *date),
+                    (value, None) => op.compare(value.is_none(), true),
+                    (None, Some(_)) => false,
+                }
+            }
+            Filter::Text(field, op, text) => {
+                let value = match field {
+                    TextField::Title => task.title.to_lowercase(),
+                    TextField::Description => task
+                        .description
+                        .as_deref()
+                        .unwrap_or_default()
+                        .to_lowercase(),
+                };
+                match op {
+                    Op::Contains => value.contains(text),
+                    Op::NotContains => !value.contains(text),
+                    _ => op.compare(value.as_str(), text.as_str()),
+                }
+            }
+        }
+    }
+}
+
+/// Splits the input into words and parentheses.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in input.split_whitespace() {
+        let mut word = word;
+        while let Some(rest) = word.strip_prefix('(') {
+            tokens.push("(".to_string());
+            word = rest;
+        }
+        let mut closing = 0;
+        while let Some(rest) = word.strip_suffix(')') {
+            closing += 1;
+            word = rest;
+        }
+        if !word.is_empty() {
+            tokens.push(word.to_string());
+        }
+        tokens.extend(std::iter::repeat_n(")".to_string(), closing));
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.parse_and()?;
+        while self.peek().map(str::to_lowercase).as_deref() == Some("or") {
+            self.next();
+            filter = Filter::Or(Box::new(filter), Box::new(self.parse_and()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.parse_not()?;
+        loop {
+            match self.peek().map(str::to_lowercase).as_deref() {
+                Some("and") => {
+                    self.next();
+                }
+                // terms next to each other are combined with and
+                Some(token) if token != "or" && token != ")" => {}
+                _ => return Ok(filter),
+            }
+            filter = Filter::And(Box::new(filter), Box::new(self.parse_not()?));
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<Filter, String> {
+        let token = self
+            .next()
+            .ok_or_else(|| "Unexpected end of filter!".to_string())?;
+        match token.to_lowercase().as_str() {
+            "not" => Ok(Filter::Not(Box::new(self.parse_not()?))),
+            "(" => {
+                let filter = self.parse_or()?;
+                match self.next().as_deref() {
+                    Some(")") => Ok(filter),
+                    _ => Err("Missing ')' in filter!".into()),
+                }
+            }
+            _ => parse_term(&token),
+        }
+    }
+}
+
+fn parse_term(term: &str) -> Result<Filter, String> {
+    if let Some(tag) = parse_tag(term, '+') {
+        return Ok(Filter::Tag(tag));
+    }
+    if let Some(tag) = parse_tag(term, '-') {
+        return Ok(Filter::Not(Box::new(Filter::Tag(tag))));
+    }
+
+    let term = term.to_lowercase();
+    match term.as_str() {
+        "done" => return Ok(Filter::Done),
+        "overdue" => return Ok(Filter::Overdue),
+        "recurring" => return Ok(Filter::Recurring),
+        "due" => return Ok(Filter::HasDue),
+        _ => {}
+    }
+
+    let (field, op, value) = OPS
+        .iter()
+        .filter_map(|(symbol, op)| {
+            term.find(symbol)
+                .map(|i| (&term[..i], *op, &term[i + symbol.len()..]))
+        })
+        .min_by_key(|(field, _, _)| field.len())
+        .ok_or_else(|| format!("Unknown filter term '{}'!", term))?;
+    if value.is_empty() {
+        return Err(format!("Missing value in '{}'!", term));
+    }
+    let is_text_op = matches!(op, Op::Contains | Op::NotContains);
+
+    let date_field = match field {
+        "due" => Some(DateField::Due),
+        "created" => Some(DateField::Created),
+        "completed" => Some(DateField::Completed),
+        _ => None,
+    };
+    if let Some(date_field) = date_field {
+        if is_text_op {
+            return Err(format!("Dates can't be compared with '~' in '{}'!", term));
+        }
+        if value == "none" {
+            if !matches!(op, Op::Eq | Op::Ne) {
+                return Err(format!("Use = or != to compare with none in '{}'!", term));
+            }
+            return Ok(Filter::Date(date_field, op, None));
+        }
+        let date = date::parse_date(value).map_err(|e| e.to_string())?;
+        return Ok(Filter::Date(date_field, op, Some(date)));
+    }
+
+    match field {
+        "importance" | "imp" | "i" if !is_text_op => {
+            Ok(Filter::Importance(op, parse_importance(value)?))
+        }
+        "title" => Ok(Filter::Text(TextField::Title, op, value.to_string())),
+        "desc" | "description" => Ok(Filter::Text(TextField::Description, op, value.to_string())),
+        "tag" if matches!(op, Op::Eq | Op::Ne) => {
+            let filter = Filter::Tag(value.to_string());
+            Ok(if op == Op::Ne {
+                Filter::Not(Box::new(filter))
+            } else {
+                filter
+            })
+        }
+        _ => Err(format!("Invalid filter term '{}'!", term)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn task(title: &str, importance: u8, due_in_days: Option<i64>, tags: &str) -> Task {
+        let due = due_in_days.map(|days| Local::now() + Duration::days(days));
+        let mut task = Task::new(title.into(), None, importance, due);
+        task.apply_tags(tags, false);
+        task
+    }
+
+    fn matches(filter: &str, task: &Task) -> bool {
+        Filter::parse(filter).unwrap().matches(task)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Filter::parse("+work -home"),
+            Ok(Filter::And(
+                Box::new(Filter::Tag("work".into())),
+                Box::new(Filter::Not(Box::new(Filter::Tag("home".into()))))
+            ))
+        );
+        assert_eq!(
+            Filter::parse("done or not (+a)"),
+            Filter::parse("(done) or (not +a)")
+        );
+        assert!(Filter::parse("").is_err());
+        assert!(Filter::parse("(done").is_err());
+        assert!(Filter::parse("done)").is_err());
+        assert!(Filter::parse("importance>=9").is_err());
+        assert!(Filter::parse("due<someday").is_err());
+        assert!(Filter::parse("colour=red").is_err());
+        assert!(Filter::parse("milk").is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let work = task("Fix the build", 2, Some(3), "+work");
+        let home = task("Buy milk", 0, None, "+home");
+        let mut old = task("Old report", 3, Some(-1), "+work");
+
+        let filter = "importance>=2 and due<1w and +work and not done";
+        assert!(matches(filter, &work));
+        assert!(!matches(filter, &home));
+        assert!(matches(filter, &old));
+        old.date_completed = Some(Local::now());
+        assert!(!matches(filter, &old));
+
+        assert!(matches("overdue", &task("a", 0, Some(-1), "")));
+        assert!(matches("due=none", &home));
+        assert!(!matches("due<1w", &home), "Tasks without a due date");
+        assert!(matches("title~MILK or +work", &home));
+        assert!(matches("+home (importance=0 or due)", &home));
+        assert!(!matches("tag!=home", &home));
+    }
+
+    #[test]
+    fn test_mentions_done() {
+        assert!(!Filter::parse("+work").unwrap().mentions_done());
+        assert!(Filter::parse("+work not done").unwrap().mentions_done());
+        assert!(Filter::parse("completed>-1w").unwrap().mentions_done());
+    }
+}
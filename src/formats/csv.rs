@@ -0,0 +1,364 @@
+//! CSV (RFC 4180) for spreadsheets: a header row and one row per task.
+//! Sub-tasks come after their parent and have its path in the `parent` column.
+//! Titles and descriptions that spreadsheets would run as formulas get a `'` in front.
+
+use super::{build_tree, Import, Node, TaskFormat};
+use crate::date::{self, DateFormat};
+use crate::interface::parse_importance;
+use crate::task::{format_path, parse_tag, Task};
+
+const COLUMNS: &[&str] = &[
+    "path",
+    "parent",
+    "id",
+    "title",
+    "description",
+    "importance",
+    "due_date",
+    "date_created",
+    "date_completed",
+    "tags",
+    "recurrence",
+];
+
+/// Characters that make spreadsheets read a field as a formula.
+const FORMULA_CHARS: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+/// Puts a `'` in front of text that starts like a formula, so spreadsheets show it as text.
+/// Text that already starts with `'` gets another one, so that `unescape_formula` keeps it.
+fn escape_formula(text: &str) -> String {
+    if text.starts_with(FORMULA_CHARS) || text.starts_with('\'') {
+        format!("'{}", text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Removes the `'` that `escape_formula` added.
+fn unescape_formula(text: &str) -> &str {
+    match text.strip_prefix('\'') {
+        Some(rest) if rest.starts_with(FORMULA_CHARS) || rest.starts_with('\'') => rest,
+        _ => text,
+    }
+}
+
+pub struct Csv {
+    pub delimiter: char,
+}
+
+impl Csv {
+    /// Parses the `--delimiter` flag: a single character or `tab`.
+    pub fn parse_delimiter(delimiter: &str) -> Result<char, String> {
+        let mut chars = delimiter.chars();
+        match (delimiter, chars.next(), chars.next()) {
+            ("tab" | "\\t", _, _) => Ok('\t'),
+            (_, Some(c), None) if c != '"' && c != '\n' && c != '\r' => Ok(c),
+            _ => Err(format!(
+                "Invalid delimiter '{}'! (use a single character like ; or tab)",
+                delimiter
+            )),
+        }
+    }
+
+    /// Quotes the field if it contains the delimiter, quotes, line breaks or surrounding spaces.
+    fn quote(&self, field: &str) -> String {
+        let needs_quotes = field.contains([self.delimiter, '"', '\n', '\r'])
+            || field.starts_with(' ')
+            || field.ends_with(' ');
+        if needs_quotes {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn write_row(&self, fields: &[String]) -> String {
+        let fields: Vec<String> = fields.iter().map(|field| self.quote(field)).collect();
+        format!("{}\r\n", fields.join(&self.delimiter.to_string()))
+    }
+
+    fn write_task(&self, task: &Task, path: &[usize], rows: &mut String) {
+        let date = |date: Option<DateFormat>| {
+            date.map(|d| d.format("%Y-%m-%dT%H:%M:%S%:z").to_string())
+                .unwrap_or_default()
+        };
+        let tags: Vec<String> = task.tags.iter().map(|tag| format!("+{}", tag)).collect();
+        *rows += &self.write_row(&[
+            format_path(path),
+            format_path(&path[..path.len() - 1]),
+            task.id.to_string(),
+            escape_formula(&task.title),
+            escape_formula(task.description.as_deref().unwrap_or_default()),
+            task.importance.to_string(),
+            date(task.due_date),
+            date(Some(task.date_created)),
+            date(task.date_completed),
+            escape_formula(&tags.join(" ")),
+            task.recurrence.map(|r| r.to_string()).unwrap_or_default(),
+        ]);
+
+        for (i, sub_task) in task.sub_tasks.iter().enumerate() {
+            self.write_task(sub_task, &[path, &[i + 1]].concat(), rows);
+        }
+    }
+
+    /// Splits the input into rows of fields, handling quoted fields with delimiters and line breaks.
+    fn parse_rows(&self, input: &str) -> Result<Vec<Vec<String>>, String> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut chars = input.chars().peekable();
+        // whether the field is quoted and whether something was read since the last row
+        let (mut quoted, mut started) = (false, false);
+
+        while let Some(c) = chars.next() {
+            started = true;
+            match c {
+                '"' if quoted => {
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                }
+                '"' if field.is_empty() => quoted = true,
+                _ if quoted => field.push(c),
+                c if c == self.delimiter => row.push(std::mem::take(&mut field)),
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                    started = false;
+                }
+                c => field.push(c),
+            }
+        }
+        if quoted {
+            return Err("Invalid CSV: a quoted field isn't closed!".into());
+        }
+        if started {
+            row.push(field);
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+/// Reads a row into a node, with the path of the task as its id.
+fn read_row(
+    number: usize,
+    columns: &[Option<&str>],
+    row: &[String],
+    warnings: &mut Vec<String>,
+) -> Option<Node> {
+    let mut task = Task::new(String::new(), None, 0, None);
+    let (mut path, mut parent) = (None, None);
+    let mut warn = |e: String| warnings.push(format!("Row {}: {}", number, e));
+
+    for (column, field) in columns.iter().zip(row.iter()) {
+        // only the title and description keep their spaces
+        let value = field.trim();
+        let (Some(column), false) = (column, value.is_empty()) else {
+            continue;
+        };
+        let date = || date::parse_date(value).map_err(|e| e.to_string());
+        let result = match *column {
+            "path" => {
+                path = Some(value.to_string());
+                Ok(())
+            }
+            "parent" => {
+                parent = Some(value.to_string());
+                Ok(())
+            }
+            "title" => {
+                task.title = unescape_formula(field).to_string();
+                Ok(())
+            }
+            "description" => {
+                task.description = Some(unescape_formula(field).to_string());
+                Ok(())
+            }
+            "importance" => parse_importance(value).map(|i| task.importance = i),
+            "due_date" => date().map(|due| task.due_date = Some(due)),
+            "date_created" => date().map(|created| task.date_created = created),
+            "date_completed" => date().map(|done| task.date_completed = Some(done)),
+            "recurrence" => value.parse().map(|r| task.recurrence = Some(r)),
+            "tags" => {
+                for tag in unescape_formula(value).split_whitespace() {
+                    let tag = tag.strip_prefix('+').unwrap_or(tag);
+                    match parse_tag(&format!("+{}", tag), '+') {
+                        Some(tag) => {
+                            task.tags.insert(tag);
+                        }
+                        None => warn(format!("invalid tag '{}'", tag)),
+                    }
+                }
+                Ok(())
+            }
+            // new ids are given to the imported tasks
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            warn(e);
+        }
+    }
+    if row.len() > columns.len() {
+        warn(format!(
+            "{} fields more than there are columns, ignored",
+            row.len() - columns.len()
+        ));
+    }
+
+    if task.title.is_empty() {
+        warn("the task has no title, skipped".into());
+        return None;
+    }
+    Some(Node {
+        source: format!("Row {}", number),
+        task: Some(task),
+        id: path,
+        parent,
+    })
+}
+
+impl TaskFormat for Csv {
+    fn export(&self, tasks: &[Task]) -> Result<String, String> {
+        let header: Vec<String> = COLUMNS.iter().map(|c| c.to_string()).collect();
+        let mut rows = self.write_row(&header);
+        for (i, task) in tasks.iter().enumerate() {
+            self.write_task(task, &[i + 1], &mut rows);
+        }
+        Ok(rows)
+    }
+
+    fn import(&self, input: &str) -> Result<Import, String> {
+        let mut import = Import::default();
+        let rows = self.parse_rows(input.trim_start_matches('\u{feff}'))?;
+        let Some((header, rows)) = rows.split_first() else {
+            return Ok(import);
+        };
+
+        let columns: Vec<Option<&str>> = header
+            .iter()
+            .map(|name| {
+                let name = name.trim().to_lowercase();
+                let column = COLUMNS.iter().find(|c| **c == name).copied();
+                if column.is_none() {
+                    import
+                        .warnings
+                        .push(format!("Column '{}' isn't a task field, ignored", name));
+                }
+                column
+            })
+            .collect();
+        if !columns.contains(&Some("title")) {
+            return Err(format!(
+                "The CSV has no title column! (the header has to name the columns, e.g. {})",
+                COLUMNS.join(&self.delimiter.to_string())
+            ));
+        }
+
+        let nodes = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.iter().any(|field| !field.trim().is_empty()))
+            // the header is row 1
+            .filter_map(|(i, row)| read_row(i + 2, &columns, row, &mut import.warnings))
+            .collect();
+        import.tasks = build_tree(nodes, &mut import.warnings);
+        Ok(import)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::tests::round_trip;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_parse_rows() {
+        let csv = Csv { delimiter: ',' };
+        assert_eq!(
+            csv.parse_rows("a,\"b, \"\"c\"\"\nd\",e\r\n\nf").unwrap(),
+            vec![vec!["a", "b, \"c\"\nd", "e"], vec![""], vec!["f"]]
+        );
+        assert!(csv.parse_rows("a,\"b").is_err());
+        assert_eq!(Csv::parse_delimiter("tab"), Ok('\t'));
+        assert!(Csv::parse_delimiter(";;").is_err());
+    }
+
+    #[test]
+    fn test_import() {
+        let input = "Title;Parent;Path;Importance;Due_Date;Colour;Tags\n\
+                     Release;;1;3;2026-11-20 17:00;red;+work q4\n\
+                     \"Write the changelog; carefully\";1;1.1;;;;\n\
+                     ;;;2;;;\n\
+                     Orphan;7;;9;;;\n";
+        let import = Csv { delimiter: ';' }.import(input).unwrap();
+        assert_eq!(import.tasks.len(), 2);
+        let release = &import.tasks[0];
+        assert_eq!(release.importance, 3);
+        assert_eq!(
+            release.due_date,
+            Some(Local.with_ymd_and_hms(2026, 11, 20, 17, 0, 0).unwrap())
+        );
+        assert_eq!(release.tags.iter().collect::<Vec<_>>(), vec!["q4", "work"]);
+        assert_eq!(release.sub_tasks[0].title, "Write the changelog; carefully");
+        assert_eq!(
+            import.warnings,
+            vec![
+                "Column 'colour' isn't a task field, ignored",
+                "Row 4: the task has no title, skipped",
+                "Row 5: Importance must be a number between 0 and 3!",
+                "Row 5: there is no parent task '7', imported at the top level",
+            ]
+        );
+        assert!(Csv { delimiter: ',' }.import("name\nfoo").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for delimiter in [',', ';', '\t'] {
+            let csv = Csv { delimiter };
+            let (exported, tasks) = round_trip(&csv, "csv");
+            assert_eq!(csv.export(&tasks).unwrap(), exported);
+        }
+    }
+
+    #[test]
+    fn test_formulas() {
+        let titles = [
+            "=HYPERLINK(\"http://example.com\", \"Click\")",
+            "+1 555 0100",
+            "-2 days",
+            "@SUM(A1:A9)",
+            "'quoted",
+            "'=already escaped",
+            " Book flights",
+        ];
+        let mut tasks: Vec<Task> = titles
+            .iter()
+            .map(|title| Task::new(title.to_string(), Some("=1+1".into()), 0, None))
+            .collect();
+        tasks[0].apply_tags("+work +q4", false);
+        let csv = Csv { delimiter: ',' };
+        let exported = csv.export(&tasks).unwrap();
+        let rows = csv.parse_rows(&exported).unwrap();
+        for row in &rows[1..] {
+            assert!(!row[3].starts_with(FORMULA_CHARS), "{}", row[3]);
+            assert_eq!(row[4], "'=1+1");
+        }
+        assert_eq!(rows[1][9], "'+q4 +work", "Tags start with a +");
+
+        let import = csv.import(&exported).unwrap();
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+        let imported: Vec<&str> = import.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(imported, titles);
+        assert_eq!(import.tasks[0].description.as_deref(), Some("=1+1"));
+        assert_eq!(import.tasks[0].tags, tasks[0].tags);
+        assert_eq!(unescape_formula("'tis"), "'tis");
+    }
+}
@@ -0,0 +1,628 @@
+//! iCalendar (RFC 5545) to-dos, which calendar apps can show.
+//! Every task is a VTODO, sub-tasks refer to the UID of their parent with RELATED-TO.
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+
+use super::{build_tree, local, Import, Node, TaskFormat};
+use crate::date::DateFormat;
+use crate::recurrence::{Recurrence, Unit, MAX_INTERVAL};
+use crate::task::{parse_tag, Task};
+
+const PRODID: &str = "-//stuff//stuff//EN";
+/// Lines longer than this (in bytes) are folded.
+const MAX_LINE_LENGTH: usize = 75;
+
+pub struct Ics;
+
+fn uid(task: &Task) -> String {
+    format!("stuff-{}", task.id)
+}
+
+fn format_date(date: DateFormat) -> String {
+    date.with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Parses a DATE-TIME in UTC (`20261017T090000Z`) or local time, or a DATE (`20261017`) at `time`.
+pub(super) fn parse_date(value: &str, time: NaiveTime) -> Option<DateFormat> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let date = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&date).with_timezone(&Local));
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Local.from_local_datetime(&date).earliest();
+    }
+    local(NaiveDate::parse_from_str(value, "%Y%m%d").ok()?, time)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => text.push('\n'),
+                Some(c) => text.push(c),
+                None => {}
+            },
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+/// Splits a list value at the commas that aren't escaped and unescapes the values.
+fn unescape_list(value: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let (mut start, mut escaped) = (0, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                values.push(unescape(&value[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    values.push(unescape(&value[start..]));
+    values
+}
+
+/// Splits a line into parts of at most `MAX_LINE_LENGTH` bytes, continued with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded += "\r\n ";
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded + "\r\n"
+}
+
+fn priority(importance: u8) -> Option<u8> {
+    match importance {
+        3 => Some(1),
+        2 => Some(5),
+        1 => Some(9),
+        _ => None,
+    }
+}
+
+fn importance(priority: u8) -> u8 {
+    match priority {
+        1..=4 => 3,
+        5 => 2,
+        6..=9 => 1,
+        _ => 0,
+    }
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn frequency(unit: Unit) -> &'static str {
+    match unit {
+        Unit::Hour => "HOURLY",
+        Unit::Day => "DAILY",
+        Unit::Week => "WEEKLY",
+        Unit::Month => "MONTHLY",
+        Unit::Year => "YEARLY",
+    }
+}
+
+/// Returns the RRULE of the recurrence. Repeating after completion can't be written as one.
+fn rrule(recurrence: Recurrence) -> Option<String> {
+    match recurrence {
+        Recurrence::Every(n, unit) => Some(format!("FREQ={};INTERVAL={}", frequency(unit), n)),
+        Recurrence::Weekday(day) => Some(format!("FREQ=WEEKLY;BYDAY={}", weekday_code(day))),
+        Recurrence::MonthlyOn(1, day) => Some(format!("FREQ=MONTHLY;BYMONTHDAY={}", day)),
+        Recurrence::MonthlyOn(n, day) => {
+            Some(format!("FREQ=MONTHLY;INTERVAL={};BYMONTHDAY={}", n, day))
+        }
+        Recurrence::AfterCompletion(..) => None,
+    }
+}
+
+/// Parses the RRULEs that can be written as a recurrence.
+fn parse_rrule(value: &str) -> Option<Recurrence> {
+    let (mut unit, mut interval, mut by_day, mut by_month_day) = (None, 1, None, None);
+    for part in value.split(';') {
+        match part.split_once('=')? {
+            ("FREQ", freq) => {
+                unit = [Unit::Hour, Unit::Day, Unit::Week, Unit::Month, Unit::Year]
+                    .into_iter()
+                    .find(|unit| frequency(*unit) == freq)
+            }
+            ("INTERVAL", n) => {
+                interval = n.parse().ok().filter(|n| (1..=MAX_INTERVAL).contains(n))?
+            }
+            ("BYDAY", day) => {
+                by_day = [
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                    Weekday::Sat,
+                    Weekday::Sun,
+                ]
+                .into_iter()
+                .find(|d| weekday_code(*d) == day)
+                .map(Some)?
+            }
+            ("BYMONTHDAY", day) => {
+                by_month_day = day
+                    .parse()
+                    .ok()
+                    .filter(|d| (1..=31).contains(d))
+                    .map(Some)?
+            }
+            ("WKST", _) => {}
+            _ => return None,
+        }
+    }
+    match (unit?, interval, by_day, by_month_day) {
+        (Unit::Week, 1, Some(day), None) => Some(Recurrence::Weekday(day)),
+        (Unit::Month, n, None, Some(day)) => Some(Recurrence::MonthlyOn(n, day)),
+        (unit, n, None, None) => Some(Recurrence::Every(n, unit)),
+        _ => None,
+    }
+}
+
+/// Writes the task and its sub-tasks as VTODOs.
+fn write_task(task: &Task, parent: Option<&Task>, now: DateFormat, lines: &mut Vec<String>) {
+    lines.push("BEGIN:VTODO".into());
+    lines.push(format!("UID:{}", uid(task)));
+    lines.push(format!("DTSTAMP:{}", format_date(now)));
+    lines.push(format!("CREATED:{}", format_date(task.date_created)));
+    lines.push(format!("SUMMARY:{}", escape(&task.title)));
+    if let Some(description) = &task.description {
+        lines.push(format!("DESCRIPTION:{}", escape(description)));
+    }
+    if let Some(due) = task.due_date {
+        lines.push(format!("DUE:{}", format_date(due)));
+    }
+    if let Some(priority) = priority(task.importance) {
+        lines.push(format!("PRIORITY:{}", priority));
+    }
+    if !task.tags.is_empty() {
+        let tags: Vec<String> = task.tags.iter().map(|tag| escape(tag)).collect();
+        lines.push(format!("CATEGORIES:{}", tags.join(",")));
+    }
+    if let Some(rule) = task.recurrence.and_then(rrule) {
+        lines.push(format!("RRULE:{}", rule));
+    }
+    if let Some(completed) = task.date_completed {
+        lines.push("STATUS:COMPLETED".into());
+        lines.push(format!("COMPLETED:{}", format_date(completed)));
+    } else {
+        lines.push("STATUS:NEEDS-ACTION".into());
+    }
+    if let Some(parent) = parent {
+        lines.push(format!("RELATED-TO:{}", uid(parent)));
+    }
+    lines.push("END:VTODO".into());
+
+    for sub_task in task.sub_tasks.iter() {
+        write_task(sub_task, Some(task), now, lines);
+    }
+}
+
+/// A content line: `NAME;PARAM=value:VALUE`.
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl<'a> Property<'a> {
+    fn parse(line: &'a str) -> Option<Property<'a>> {
+        // the value starts after the first colon that isn't in a quoted parameter
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(i, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(i),
+            _ => None,
+        })?;
+        let mut parts = line[..colon].split(';');
+        let name = parts.next()?.to_uppercase();
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"')))
+            .collect();
+        Some(Property {
+            name,
+            params,
+            value: &line[colon + 1..],
+        })
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Joins the folded lines back together.
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in input.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => *last += rest,
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Parses the value of a date property, warning if it's invalid.
+fn date_value(
+    property: &Property,
+    time: NaiveTime,
+    source: &str,
+    warnings: &mut Vec<String>,
+) -> Option<DateFormat> {
+    let date = parse_date(property.value, time);
+    if date.is_none() {
+        warnings.push(format!(
+            "{}: invalid {} '{}'",
+            source, property.name, property.value
+        ));
+    }
+    // time zones aren't converted, only UTC and local times are known
+    if let Some(tzid) = property.param("TZID").filter(|_| date.is_some()) {
+        if !property.value.ends_with('Z') {
+            warnings.push(format!(
+                "{}: {} in time zone '{}' was read as local time",
+                source, property.name, tzid
+            ));
+        }
+    }
+    date
+}
+
+/// Reads a property of a VTODO into the node.
+fn read_property(property: &Property, node: &mut Node, warnings: &mut Vec<String>) {
+    let Node {
+        source,
+        task,
+        id,
+        parent,
+    } = node;
+    let Some(task) = task.as_mut() else {
+        return;
+    };
+    let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+
+    match property.name.as_str() {
+        "UID" => *id = Some(property.value.to_string()),
+        "SUMMARY" => task.title = unescape(property.value),
+        "DESCRIPTION" => task.description = Some(unescape(property.value)),
+        // due dates without a time are at the end of the day, like `tomorrow`
+        "DUE" => {
+            let end_of_day = NaiveTime::from_hms_opt(23, 59, 0).unwrap();
+            task.due_date = date_value(property, end_of_day, source, warnings);
+        }
+        "CREATED" => {
+            if let Some(created) = date_value(property, midnight, source, warnings) {
+                task.date_created = created;
+            }
+        }
+        // the task is done even if the date is invalid
+        "COMPLETED" => match parse_date(property.value, midnight) {
+            Some(completed) => task.date_completed = Some(completed),
+            None => {
+                warnings.push(format!(
+                    "{}: invalid COMPLETED '{}', marked as done now",
+                    source, property.value
+                ));
+                task.date_completed = task.date_completed.or(Some(Local::now()));
+            }
+        },
+        "STATUS" => match property.value.to_uppercase().as_str() {
+            "COMPLETED" => task.date_completed = task.date_completed.or(Some(Local::now())),
+            "NEEDS-ACTION" => {}
+            _ => warnings.push(format!(
+                "{}: STATUS '{}' isn't supported, imported as {}",
+                source,
+                property.value,
+                if task.is_done() { "done" } else { "not done" }
+            )),
+        },
+        "PRIORITY" => match property.value.parse() {
+            Ok(priority) => task.importance = importance(priority),
+            Err(_) => warnings.push(format!("{}: invalid PRIORITY '{}'", source, property.value)),
+        },
+        "CATEGORIES" => {
+            for category in unescape_list(property.value) {
+                let tag = category.trim().replace(char::is_whitespace, "-");
+                match parse_tag(&format!("+{}", tag), '+') {
+                    Some(tag) => {
+                        task.tags.insert(tag);
+                    }
+                    None if tag.is_empty() => {}
+                    None => warnings.push(format!(
+                        "{}: category '{}' can't be a tag",
+                        source, category
+                    )),
+                }
+            }
+        }
+        "RRULE" => match parse_rrule(property.value) {
+            Some(recurrence) => task.recurrence = Some(recurrence),
+            None => warnings.push(format!(
+                "{}: RRULE '{}' isn't supported, the task won't repeat",
+                source, property.value
+            )),
+        },
+        "RELATED-TO" => match property.param("RELTYPE") {
+            None | Some("PARENT") => *parent = Some(property.value.to_string()),
+            Some(reltype) => warnings.push(format!(
+                "{}: RELATED-TO with RELTYPE={} isn't supported",
+                source, reltype
+            )),
+        },
+        _ => {}
+    }
+}
+
+impl TaskFormat for Ics {
+    fn export(&self, tasks: &[Task]) -> Result<String, String> {
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            format!("PRODID:{}", PRODID),
+        ];
+        let now = Local::now();
+        for task in tasks {
+            write_task(task, None, now, &mut lines);
+        }
+        lines.push("END:VCALENDAR".into());
+        Ok(lines.iter().map(|line| fold(line)).collect())
+    }
+
+    fn import(&self, input: &str) -> Result<Import, String> {
+        let lines = unfold(input);
+        if !lines
+            .first()
+            .is_some_and(|line| line.trim().eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+        {
+            return Err("Not an iCalendar file! (it has to start with BEGIN:VCALENDAR)".into());
+        }
+
+        let mut import = Import::default();
+        let mut nodes = Vec::new();
+        let mut components: Vec<String> = Vec::new();
+        let mut node: Option<Node> = None;
+        let mut skipped: Vec<(String, usize)> = Vec::new();
+
+        for (i, line) in lines.iter().enumerate() {
+            let Some(property) = Property::parse(line.trim_end()) else {
+                if !line.trim().is_empty() {
+                    import
+                        .warnings
+                        .push(format!("Line {}: invalid line '{}'", i + 1, line));
+                }
+                continue;
+            };
+            let component = property.value.trim().to_uppercase();
+            match property.name.as_str() {
+                "BEGIN" => {
+                    if component == "VTODO" {
+                        node = Some(Node {
+                            source: format!("VTODO {}", nodes.len() + 1),
+                            task: Some(Task::new(String::new(), None, 0, None)),
+                            id: None,
+                            parent: None,
+                        });
+                    } else if components.len() == 1 && component != "VTIMEZONE" {
+                        match skipped.iter_mut().find(|(name, _)| *name == component) {
+                            Some((_, count)) => *count += 1,
+                            None => skipped.push((component.clone(), 1)),
+                        }
+                    }
+                    components.push(component);
+                }
+                "END" => {
+                    components.pop();
+                    if component != "VTODO" {
+                        continue;
+                    }
+                    if let Some(node) = node.take() {
+                        if node.task.as_ref().is_some_and(|task| task.title.is_empty()) {
+                            import
+                                .warnings
+                                .push(format!("{}: it has no SUMMARY, skipped", node.source));
+                        } else {
+                            nodes.push(node);
+                        }
+                    }
+                }
+                _ if components.last().map(String::as_str) == Some("VTODO") => {
+                    if let Some(node) = node.as_mut() {
+                        read_property(&property, node, &mut import.warnings);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (component, count) in skipped {
+            import.warnings.push(format!(
+                "Skipped {} {} {}",
+                count,
+                component,
+                if count == 1 { "entry" } else { "entries" }
+            ));
+        }
+        import.tasks = build_tree(nodes, &mut import.warnings);
+        Ok(import)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::tests::{round_trip, sample_tasks, save_and_load};
+
+    #[test]
+    fn test_import() {
+        let input = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+SUMMARY:Meeting\r
+END:VEVENT\r
+BEGIN:VTODO\r
+UID:abc\r
+SUMMARY:Write the report\\, then send it to\r
+  the team\r
+DESCRIPTION:Line one\\nLine two\r
+DUE;VALUE=DATE:20261120\r
+CREATED:20261001T080000Z\r
+PRIORITY:2\r
+CATEGORIES:Work,Q4 plans\r
+RRULE:FREQ=WEEKLY;BYDAY=FR\r
+BEGIN:VALARM\r
+SUMMARY:Alarm\r
+END:VALARM\r
+END:VTODO\r
+BEGIN:VTODO\r
+UID:def\r
+RELATED-TO:abc\r
+SUMMARY:Collect numbers\r
+STATUS:COMPLETED\r
+COMPLETED:yesterday\r
+RRULE:FREQ=DAILY;COUNT=3\r
+END:VTODO\r
+BEGIN:VTODO\r
+SUMMARY:Review numbers\r
+STATUS:IN-PROCESS\r
+DUE;TZID=America/New_York:20261120T170000\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+        let import = Ics.import(input).unwrap();
+        assert_eq!(import.tasks.len(), 2);
+        let task = &import.tasks[0];
+        assert_eq!(task.title, "Write the report, then send it to the team");
+        assert_eq!(task.description.as_deref(), Some("Line one\nLine two"));
+        assert_eq!(
+            task.due_date.unwrap().format("%Y-%m-%d %H:%M").to_string(),
+            "2026-11-20 23:59"
+        );
+        assert_eq!(
+            task.date_created,
+            Utc.with_ymd_and_hms(2026, 10, 1, 8, 0, 0)
+                .unwrap()
+                .with_timezone(&Local)
+        );
+        assert_eq!(task.importance, 3);
+        assert_eq!(
+            task.tags.iter().collect::<Vec<_>>(),
+            vec!["q4-plans", "work"]
+        );
+        assert_eq!(task.recurrence, Some(Recurrence::Weekday(Weekday::Fri)));
+
+        assert_eq!(task.sub_tasks[0].title, "Collect numbers");
+        assert!(task.sub_tasks[0].is_done());
+        assert!(!import.tasks[1].is_done());
+        assert_eq!(
+            import.tasks[1].due_date,
+            Local.with_ymd_and_hms(2026, 11, 20, 17, 0, 0).single()
+        );
+        assert_eq!(
+            import.warnings,
+            vec![
+                "VTODO 2: invalid COMPLETED 'yesterday', marked as done now",
+                "VTODO 2: RRULE 'FREQ=DAILY;COUNT=3' isn't supported, the task won't repeat",
+                "VTODO 3: STATUS 'IN-PROCESS' isn't supported, imported as not done",
+                "VTODO 3: DUE in time zone 'America/New_York' was read as local time",
+                "Skipped 1 VEVENT entry",
+            ]
+        );
+
+        assert!(Ics.import("not a calendar").is_err());
+        assert_eq!(parse_rrule("FREQ=DAILY;INTERVAL=300000000"), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (exported, tasks) = round_trip(&Ics, "ics");
+        assert!(exported
+            .lines()
+            .all(|line| line.len() <= MAX_LINE_LENGTH + 1));
+
+        // DTSTAMP is the time of the export, so compare the tasks instead
+        fn assert_same(tasks: &[Task], expected: &[Task]) {
+            assert_eq!(tasks.len(), expected.len());
+            for (task, expected) in tasks.iter().zip(expected) {
+                assert_eq!(task.title, expected.title);
+                assert_eq!(task.description, expected.description);
+                assert_eq!(task.importance, expected.importance);
+                assert_eq!(task.due_date, expected.due_date);
+                assert_eq!(task.date_created, expected.date_created);
+                assert_eq!(task.date_completed, expected.date_completed);
+                assert_eq!(task.tags, expected.tags);
+                assert_eq!(task.recurrence, expected.recurrence);
+                assert_same(&task.sub_tasks, &expected.sub_tasks);
+            }
+        }
+        assert_same(&tasks, &sample_tasks());
+    }
+
+    #[test]
+    fn test_import_malicious() {
+        let input = "BEGIN:VCALENDAR
+BEGIN:VTODO
+SUMMARY:Huge
+DUE:99999999T000000Z
+PRIORITY:99999999999
+RRULE:FREQ=YEARLY;INTERVAL=4294967296
+END:VTODO
+BEGIN:VTODO
+SUMMARY:Looping
+UID:a
+RELATED-TO:a
+END:VTODO
+";
+        let import = Ics.import(input).unwrap();
+        assert_eq!(
+            import.warnings,
+            vec![
+                "VTODO 1: invalid DUE '99999999T000000Z'",
+                "VTODO 1: invalid PRIORITY '99999999999'",
+                "VTODO 1: RRULE 'FREQ=YEARLY;INTERVAL=4294967296' isn't supported, the task won't repeat",
+                "VTODO 2: the parent task 'a' forms a cycle, imported at the top level",
+            ]
+        );
+        assert_eq!(save_and_load(import.tasks, "ics-malicious").len(), 2);
+    }
+}
@@ -0,0 +1,347 @@
+//! Markdown checklists, e.g. for status reports:
+//!
+//! ```text
+//! - [ ] Buy milk +home _(importance 2, due 2026-10-18 23:59)_
+//!   Description lines are indented under the task.
+//!   - [x] Find the wallet _(done 2026-10-17 09:30)_
+//! ```
+//!
+//! Sub-tasks are indented under their parent. Text that would be read as a list item or
+//! annotations is escaped with a backslash.
+
+use chrono::Local;
+
+use super::{Import, TaskFormat, MAX_DEPTH};
+use crate::date::{self, DateFormat};
+use crate::interface::parse_importance;
+use crate::task::Task;
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+const INDENT: &str = "  ";
+
+pub struct Markdown;
+
+fn format_date(date: DateFormat) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+/// Returns the annotations written after the title, e.g. `importance 2, due 2026-10-18 23:59`.
+fn annotations(task: &Task) -> Vec<String> {
+    let mut annotations = Vec::new();
+    if task.importance > 0 {
+        annotations.push(format!("importance {}", task.importance));
+    }
+    if let Some(due) = task.due_date {
+        annotations.push(format!("due {}", format_date(due)));
+    }
+    if let Some(recurrence) = task.recurrence {
+        annotations.push(format!("repeats {}", recurrence));
+    }
+    if let Some(completed) = task.date_completed {
+        annotations.push(format!("done {}", format_date(completed)));
+    }
+    annotations
+}
+
+/// Returns whether the description line would be read as a list item, or starts with the escape.
+fn needs_escape(line: &str) -> bool {
+    parse_item(line).is_some() || line.starts_with('\\')
+}
+
+fn write_task(task: &Task, depth: usize, lines: &mut Vec<String>) {
+    let indent = INDENT.repeat(depth);
+    let mut line = format!(
+        "{}- [{}] {}",
+        indent,
+        if task.is_done() { "x" } else { " " },
+        // so that the title isn't read as annotations
+        task.title.replace("_(", "\\_(")
+    );
+    for tag in task.tags.iter() {
+        line += &format!(" +{}", tag);
+    }
+    let annotations = annotations(task);
+    if !annotations.is_empty() {
+        line += &format!(" _({})_", annotations.join(", "));
+    }
+    lines.push(line);
+
+    if let Some(description) = &task.description {
+        for desc_line in description.lines().filter(|l| !l.trim().is_empty()) {
+            let desc_line = desc_line.trim();
+            let escape = if needs_escape(desc_line) { "\\" } else { "" };
+            lines.push(format!("{}{}{}{}", indent, INDENT, escape, desc_line));
+        }
+    }
+    for sub_task in task.sub_tasks.iter() {
+        write_task(sub_task, depth + 1, lines);
+    }
+}
+
+/// Splits off the annotations in `_(...)_` at the end of the text, unless it's escaped.
+fn split_annotations(text: &str) -> (&str, Option<&str>) {
+    text.strip_suffix(")_")
+        .and_then(|rest| rest.rsplit_once("_("))
+        .filter(|(title, _)| !title.ends_with('\\'))
+        .map_or((text, None), |(title, annotations)| {
+            (title.trim_end(), Some(annotations))
+        })
+}
+
+/// Reads the annotations into the task.
+fn apply_annotations(
+    task: &mut Task,
+    annotations: &str,
+    number: usize,
+    warnings: &mut Vec<String>,
+) {
+    for annotation in annotations.split(',').map(str::trim) {
+        let (name, value) = annotation.split_once(' ').unwrap_or((annotation, ""));
+        let result = match name {
+            "importance" => parse_importance(value).map(|i| task.importance = i),
+            "due" => date::parse_date(value)
+                .map(|due| task.due_date = Some(due))
+                .map_err(|e| e.to_string()),
+            "done" => date::parse_date(value)
+                .map(|done| task.date_completed = Some(done))
+                .map_err(|e| e.to_string()),
+            "repeats" => value.parse().map(|r| task.recurrence = Some(r)),
+            _ => Err(format!("unknown annotation '{}'", annotation)),
+        };
+        if let Err(e) = result {
+            warnings.push(format!("Line {}: {}", number, e));
+        }
+    }
+}
+
+/// Parses a list item: `- [ ] text`, `- [x] text` or `- text` (also with `*` or `+`).
+/// Returns whether it's checked and the text.
+fn parse_item(line: &str) -> Option<(bool, &str)> {
+    let rest = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))?;
+    if let Some(text) = rest.strip_prefix("[ ] ") {
+        Some((false, text))
+    } else if let Some(text) = rest
+        .strip_prefix("[x] ")
+        .or_else(|| rest.strip_prefix("[X] "))
+    {
+        Some((true, text))
+    } else {
+        Some((false, rest))
+    }
+}
+
+/// Returns the width of the indentation of the line, counting tabs as 4 spaces.
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// Adds the task at the top of the stack to the one below it, or to the top level.
+fn pop(stack: &mut Vec<(usize, Task)>, tasks: &mut Vec<Task>) {
+    if let Some((_, task)) = stack.pop() {
+        match stack.last_mut() {
+            Some((_, parent)) => parent.add_sub_task(task),
+            None => tasks.push(task),
+        }
+    }
+}
+
+impl TaskFormat for Markdown {
+    fn export(&self, tasks: &[Task]) -> Result<String, String> {
+        let mut lines = Vec::new();
+        for task in tasks {
+            write_task(task, 0, &mut lines);
+        }
+        Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+    }
+
+    fn import(&self, input: &str) -> Result<Import, String> {
+        let mut import = Import::default();
+        // the tasks that can still get sub-tasks, with their indentation
+        let mut stack: Vec<(usize, Task)> = Vec::new();
+
+        for (i, line) in input.lines().enumerate() {
+            let number = i + 1;
+            let indent = indent_width(line);
+            let trimmed = line.trim();
+            // only headings at the start of the line, `#` text under a task is its description
+            if trimmed.is_empty() || (indent == 0 && trimmed.starts_with('#')) {
+                continue;
+            }
+
+            let Some((done, text)) = parse_item(trimmed) else {
+                // text indented under a task is its description
+                match stack.last_mut() {
+                    Some((task_indent, task)) if indent > *task_indent => {
+                        let trimmed = match trimmed.strip_prefix('\\') {
+                            Some(rest) if needs_escape(rest) => rest,
+                            _ => trimmed,
+                        };
+                        task.description = Some(match task.description.take() {
+                            Some(description) => format!("{}\n{}", description, trimmed),
+                            None => trimmed.to_string(),
+                        });
+                    }
+                    _ => import
+                        .warnings
+                        .push(format!("Line {}: not a list item, skipped", number)),
+                }
+                continue;
+            };
+
+            while stack.last().is_some_and(|(top, _)| *top >= indent) {
+                pop(&mut stack, &mut import.tasks);
+            }
+
+            let (title, annotations) = split_annotations(text);
+            let mut task = Task::new(String::new(), None, 0, None);
+            task.title = task.apply_tags(&title.replace("\\_(", "_("), false);
+            if let Some(annotations) = annotations {
+                apply_annotations(&mut task, annotations, number, &mut import.warnings);
+            }
+            if done && task.date_completed.is_none() {
+                task.date_completed = Some(Local::now());
+            } else if !done && task.date_completed.is_some() {
+                import.warnings.push(format!(
+                    "Line {}: the task isn't checked, so it's not done",
+                    number
+                ));
+                task.date_completed = None;
+            }
+            if task.title.is_empty() {
+                import
+                    .warnings
+                    .push(format!("Line {}: the task has no title, skipped", number));
+                continue;
+            }
+            if stack.len() == MAX_DEPTH {
+                import.warnings.push(format!(
+                    "Line {}: it would be nested more than {} tasks deep, imported next to its parent",
+                    number, MAX_DEPTH
+                ));
+                pop(&mut stack, &mut import.tasks);
+            }
+            stack.push((indent, task));
+        }
+
+        while !stack.is_empty() {
+            pop(&mut stack, &mut import.tasks);
+        }
+        if import.tasks.is_empty() && !import.warnings.is_empty() {
+            return Err("No checklist items found!".into());
+        }
+        Ok(import)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::tests::{round_trip, sample_tasks, save_and_load};
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_import() {
+        let input = "\
+# Status
+- [ ] Release +work _(importance 3, due 2026-11-20 17:00)_
+  Ship it before the holidays.
+  # 1 blocker left
+  - [x] Write the changelog
+    * [X] Collect the PRs _(done 2026-10-16 10:00)_
+  - [ ] Tag the version _(colour red)_
+- Plain item
+Some notes
+";
+        let import = Markdown.import(input).unwrap();
+        let tasks = &import.tasks;
+        assert_eq!(tasks.len(), 2);
+
+        let release = &tasks[0];
+        assert_eq!(release.title, "Release");
+        assert!(release.tags.contains("work"));
+        assert_eq!(release.importance, 3);
+        assert_eq!(
+            release.due_date,
+            Some(Local.with_ymd_and_hms(2026, 11, 20, 17, 0, 0).unwrap())
+        );
+        assert_eq!(
+            release.description.as_deref(),
+            Some("Ship it before the holidays.\n# 1 blocker left")
+        );
+        assert_eq!(release.sub_tasks.len(), 2);
+        assert!(release.sub_tasks[0].is_done());
+        assert_eq!(
+            release.sub_tasks[0].sub_tasks[0].date_completed,
+            Some(Local.with_ymd_and_hms(2026, 10, 16, 10, 0, 0).unwrap())
+        );
+        assert_eq!(release.sub_tasks[1].title, "Tag the version");
+        assert_eq!(tasks[1].title, "Plain item");
+        assert!(!tasks[1].is_done());
+
+        assert_eq!(
+            import.warnings,
+            vec![
+                "Line 7: unknown annotation 'colour red'",
+                "Line 9: not a list item, skipped"
+            ]
+        );
+        assert!(Markdown.import("Just some text").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (exported, tasks) = round_trip(&Markdown, "md");
+        assert_eq!(Markdown.export(&tasks).unwrap(), exported);
+        assert_eq!(tasks[0].description, sample_tasks()[0].description);
+
+        let mut tasks = vec![
+            Task::new(
+                "Read _(the book)_".into(),
+                Some(
+                    "- not a sub-task\n* nor this\n+ [x] or this\n\\ stays\n# not a heading".into(),
+                ),
+                0,
+                None,
+            ),
+            Task::new("Escaped \\_(twice)_".into(), None, 2, None),
+        ];
+        tasks[1].description = Some("\\- keeps its backslash".into());
+        let exported = Markdown.export(&tasks).unwrap();
+        let import = Markdown.import(&exported).unwrap();
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+        assert_eq!(import.tasks.len(), 2);
+        for (imported, task) in import.tasks.iter().zip(&tasks) {
+            assert_eq!(imported.title, task.title);
+            assert_eq!(imported.description, task.description);
+            assert_eq!(imported.importance, task.importance);
+            assert!(imported.sub_tasks.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_import_deep() {
+        let input: String = (0..40)
+            .map(|i| format!("{}- [ ] Step {}\n", INDENT.repeat(i), i + 1))
+            .collect();
+        let import = Markdown.import(&input).unwrap();
+        assert_eq!(import.warnings.len(), 8, "{:?}", import.warnings);
+        assert_eq!(
+            import.warnings[0],
+            "Line 33: it would be nested more than 32 tasks deep, imported next to its parent"
+        );
+
+        let tasks = save_and_load(import.tasks, "md-deep");
+        let mut deepest = &tasks[0];
+        for _ in 1..MAX_DEPTH - 1 {
+            deepest = &deepest.sub_tasks[0];
+        }
+        assert_eq!(deepest.sub_tasks.len(), 9);
+        assert_eq!(deepest.sub_tasks[8].title, "Step 40");
+    }
+}
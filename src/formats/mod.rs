@@ -0,0 +1,252 @@
+//! Formats of other todo apps the tasks can be imported from and exported to.
+
+mod csv;
+mod ics;
+mod markdown;
+mod taskwarrior;
+mod todotxt;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone};
+
+use crate::args::Args;
+use crate::date::DateFormat;
+use crate::task::Task;
+
+use csv::Csv;
+use ics::Ics;
+use markdown::Markdown;
+use taskwarrior::Taskwarrior;
+use todotxt::TodoTxt;
+
+/// How deep tasks can be nested, since the save file couldn't be read anymore if they were
+/// nested much deeper. Deeper imported tasks are put higher up.
+pub const MAX_DEPTH: usize = 32;
+
+/// The format names accepted by `find`, for help and error messages.
+pub const NAMES: &[&str] = &["todotxt", "ics", "md", "csv", "taskwarrior"];
+
+pub trait TaskFormat {
+    /// Writes the tasks and all their sub-tasks.
+    fn export(&self, _tasks: &[Task]) -> Result<String, String> {
+        Err("Tasks can't be exported in this format!".into())
+    }
+
+    /// Reads the tasks. Anything that couldn't be read is reported in the warnings.
+    fn import(&self, input: &str) -> Result<Import, String>;
+}
+
+/// The tasks read by an import.
+#[derive(Debug, Default)]
+pub struct Import {
+    pub tasks: Vec<Task>,
+    /// Things that were skipped or couldn't be mapped onto tasks.
+    pub warnings: Vec<String>,
+}
+
+/// Returns the format with the name, configured by the flags in `args`.
+pub fn find(name: &str, args: &Args) -> Result<Box<dyn TaskFormat>, String> {
+    match name.to_lowercase().as_str() {
+        "todotxt" | "todo.txt" => Ok(Box::new(TodoTxt)),
+        "ics" | "ical" | "icalendar" => Ok(Box::new(Ics)),
+        "md" | "markdown" => Ok(Box::new(Markdown)),
+        "csv" => {
+            let delimiter = match args.get(&["delimiter"]) {
+                Some(delimiter) => Csv::parse_delimiter(delimiter)?,
+                None => ',',
+            };
+            Ok(Box::new(Csv { delimiter }))
+        }
+        "taskwarrior" | "tw" => Ok(Box::new(Taskwarrior)),
+        _ => Err(format!(
+            "Unknown format '{}'! (use one of: {})",
+            name,
+            NAMES.join(", ")
+        )),
+    }
+}
+
+/// Returns the local date and time, or None if the time doesn't exist on that day.
+fn local(date: NaiveDate, time: NaiveTime) -> Option<DateFormat> {
+    Local.from_local_datetime(&date.and_time(time)).earliest()
+}
+
+/// Counts the tasks and all their sub-tasks.
+pub fn count(tasks: &[Task]) -> usize {
+    tasks.iter().map(|task| 1 + count(&task.sub_tasks)).sum()
+}
+
+/// A task that was read, before it's put into the hierarchy.
+/// Formats without nesting refer to the parent of a task by some id.
+struct Node {
+    /// Where the task was read from, for the warnings, e.g. "Line 3".
+    source: String,
+    task: Option<Task>,
+    id: Option<String>,
+    parent: Option<String>,
+}
+
+/// Takes the task of the node and adds the tasks of the nodes that have it as the parent as its sub-tasks.
+/// Sub-tasks that would be nested deeper than `MAX_DEPTH` are left in `too_deep` instead.
+fn build(
+    i: usize,
+    depth: usize,
+    nodes: &mut [Node],
+    children: &HashMap<String, Vec<usize>>,
+    too_deep: &mut VecDeque<usize>,
+) -> Option<Task> {
+    let mut task = nodes[i].task.take()?;
+    let ids = nodes[i].id.as_ref().and_then(|id| children.get(id));
+    for &child in ids.into_iter().flatten() {
+        if depth == MAX_DEPTH {
+            too_deep.push_back(child);
+        } else if let Some(sub_task) = build(child, depth + 1, nodes, children, too_deep) {
+            task.add_sub_task(sub_task);
+        }
+    }
+    Some(task)
+}
+
+/// Builds the task of the node, followed by the sub-tasks that would be nested too deep.
+fn build_top_level(
+    i: usize,
+    nodes: &mut [Node],
+    children: &HashMap<String, Vec<usize>>,
+    warnings: &mut Vec<String>,
+) -> Vec<Task> {
+    let mut too_deep = VecDeque::new();
+    let mut tasks: Vec<Task> = build(i, 1, nodes, children, &mut too_deep)
+        .into_iter()
+        .collect();
+    while let Some(i) = too_deep.pop_front() {
+        warnings.push(format!(
+            "{}: it would be nested more than {} tasks deep under '{}', imported at the top level",
+            nodes[i].source,
+            MAX_DEPTH,
+            nodes[i].parent.as_deref().unwrap_or_default()
+        ));
+        tasks.extend(build(i, 1, nodes, children, &mut too_deep));
+    }
+    tasks
+}
+
+/// Puts the tasks of the nodes under their parents, keeping their order.
+/// Tasks whose parent doesn't exist or that would be nested too deep are put at the top level.
+fn build_tree(mut nodes: Vec<Node>, warnings: &mut Vec<String>) -> Vec<Task> {
+    let mut children: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, node) in nodes.iter().enumerate() {
+        if let Some(parent) = &node.parent {
+            children.entry(parent.clone()).or_default().push(i);
+        }
+    }
+    let ids: HashSet<&String> = nodes.iter().filter_map(|node| node.id.as_ref()).collect();
+    let roots: Vec<usize> = (0..nodes.len())
+        .filter(|&i| match &nodes[i].parent {
+            None => true,
+            Some(parent) if ids.contains(&parent) => false,
+            Some(parent) => {
+                warnings.push(format!(
+                    "{}: there is no parent task '{}', imported at the top level",
+                    nodes[i].source, parent
+                ));
+                true
+            }
+        })
+        .collect();
+
+    let mut tasks = Vec::new();
+    for i in roots {
+        tasks.extend(build_top_level(i, &mut nodes, &children, warnings));
+    }
+    // tasks that are their own ancestors can't be reached from the top level
+    for i in 0..nodes.len() {
+        if nodes[i].task.is_some() {
+            warnings.push(format!(
+                "{}: the parent task '{}' forms a cycle, imported at the top level",
+                nodes[i].source,
+                nodes[i].parent.as_deref().unwrap_or_default()
+            ));
+            tasks.extend(build_top_level(i, &mut nodes, &children, warnings));
+        }
+    }
+    tasks
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::recurrence::{Recurrence, Unit};
+    use crate::storage::{JsonFileStorage, MemoryStorage, Storage};
+    use crate::task::TaskList;
+    use chrono::Duration;
+
+    /// Tasks using every field, with a done sub-task that has a sub-task of its own.
+    pub fn sample_tasks() -> Vec<Task> {
+        // the formats with the least precision only keep minutes
+        let now = Local
+            .timestamp_opt(Local::now().timestamp() / 60 * 60, 0)
+            .unwrap();
+        let mut trip = Task::new(
+            "Plan \"the\" trip; with a comma, a backslash \\ and more words than fit on one line"
+                .into(),
+            Some("Somewhere warm\nwith a beach".into()),
+            2,
+            Some(now + Duration::days(3)),
+        );
+        trip.date_created = now;
+        trip.apply_tags("+travel +summer", false);
+        trip.recurrence = Some(Recurrence::Every(2, Unit::Week));
+        let mut flights = Task::new("Book flights".into(), None, 3, None);
+        flights.date_created = now;
+        flights.date_completed = Some(now - Duration::days(1));
+        let mut prices = Task::new("Compare prices".into(), None, 0, None);
+        prices.date_created = now;
+        flights.add_sub_task(prices);
+        trip.add_sub_task(flights);
+        let mut relax = Task::new("Relax".into(), None, 0, None);
+        relax.date_created = now;
+
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        task_list.add_task(trip);
+        task_list.add_task(relax);
+        task_list.tasks
+    }
+
+    /// Adds the tasks like the import command, saves them to a file and loads them back.
+    pub fn save_and_load(tasks: Vec<Task>, name: &str) -> Vec<Task> {
+        let dir = std::env::temp_dir().join(format!(
+            "stuff-formats-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let path = dir.join("saved_stuff.json");
+        let mut task_list = TaskList::new(Box::new(JsonFileStorage::new(path.clone())));
+        for task in tasks {
+            task_list.insert(&[], task).unwrap();
+        }
+        task_list.save().unwrap();
+
+        let document = JsonFileStorage::new(path).load();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        task_list.restore(document.unwrap().tasks);
+        task_list.tasks
+    }
+
+    /// Exports the sample tasks and imports, saves and loads them again.
+    /// Returns the export and the loaded tasks.
+    pub fn round_trip(format: &dyn TaskFormat, name: &str) -> (String, Vec<Task>) {
+        let exported = format.export(&sample_tasks()).unwrap();
+        let import = format.import(&exported).unwrap();
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+        (exported, save_and_load(import.tasks, name))
+    }
+
+    #[test]
+    fn test_count() {
+        let tasks = sample_tasks();
+        assert_eq!(count(&tasks), 4);
+        assert_eq!(count(&save_and_load(tasks, "count")), 4);
+    }
+}
@@ -0,0 +1,353 @@
+//! The JSON written by Taskwarrior's `task export`: an array of tasks, or one task per line
+//! in older versions. Import only.
+//!
+//! A task that depends on others gets them as sub-tasks, since they have to be done first.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveTime;
+use serde_json::{Map, Value};
+
+use super::{build_tree, ics, Import, Node, TaskFormat};
+use crate::date::DateFormat;
+use crate::task::{format_count, parse_tag, Task};
+
+/// Fields that are only used by Taskwarrior itself, so there's nothing to map them onto.
+const INTERNAL_FIELDS: &[&str] = &[
+    "uuid", "id", "urgency", "modified", "mask", "imask", "parent",
+];
+
+pub struct Taskwarrior;
+
+/// A task that was read, with the uuids of the tasks it depends on.
+struct Entry {
+    node: Node,
+    depends: Vec<String>,
+}
+
+/// Parses the whole input as a JSON array, or else each line as a task.
+fn parse_tasks(input: &str) -> Result<Vec<Value>, String> {
+    if let Ok(Value::Array(tasks)) = serde_json::from_str(input) {
+        return Ok(tasks);
+    }
+    input
+        .lines()
+        .map(|line| line.trim().trim_end_matches(','))
+        .filter(|line| !line.is_empty() && *line != "[" && *line != "]")
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Invalid Taskwarrior export: {} (use `task export`)", e))
+        })
+        .collect()
+}
+
+/// Parses a date like `20261017T093000Z`, using the time for dates without one.
+fn parse_date(value: &Value, time: NaiveTime) -> Result<DateFormat, String> {
+    let text = value.as_str().unwrap_or_default();
+    ics::parse_date(text, time).ok_or_else(|| format!("invalid date '{}'", value))
+}
+
+/// Returns the uuids in `depends`, which is an array or a comma-separated string in older versions.
+fn parse_depends(value: &Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::Array(uuids) => uuids
+            .iter()
+            .map(|uuid| uuid.as_str().map(str::to_string))
+            .collect::<Option<_>>()
+            .ok_or_else(|| format!("invalid dependencies {}", value)),
+        Value::String(uuids) => Ok(uuids
+            .split(',')
+            .map(|uuid| uuid.trim().to_string())
+            .collect()),
+        _ => Err(format!("invalid dependencies {}", value)),
+    }
+}
+
+/// Reads a task, counting the fields that couldn't be mapped in `unmapped`.
+fn read_task(
+    number: usize,
+    fields: &Map<String, Value>,
+    unmapped: &mut BTreeMap<String, usize>,
+    warnings: &mut Vec<String>,
+) -> Option<Entry> {
+    let mut task = Task::new(String::new(), None, 0, None);
+    let mut depends = Vec::new();
+    let mut warn = |e: String| warnings.push(format!("Task {}: {}", number, e));
+    let status = fields
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or("pending");
+
+    match status {
+        "pending" | "waiting" | "completed" => {}
+        "deleted" => {
+            warn("the task was deleted, skipped".into());
+            return None;
+        }
+        "recurring" => {
+            warn(format!(
+                "the task repeats {}, only its instances are imported and they don't repeat",
+                fields.get("recur").unwrap_or(&Value::Null)
+            ));
+            return None;
+        }
+        _ => warn(format!("unknown status '{}', imported as pending", status)),
+    }
+
+    for (name, value) in fields {
+        let result = match name.as_str() {
+            "description" => value
+                .as_str()
+                .map(|title| task.title = title.trim().to_string())
+                .ok_or_else(|| format!("invalid description {}", value)),
+            "priority" => match value.as_str() {
+                Some("H") => Ok(3),
+                Some("M") => Ok(2),
+                Some("L") => Ok(1),
+                _ => Err(format!(
+                    "priority {} isn't H, M or L, imported with importance 0",
+                    value
+                )),
+            }
+            .map(|importance| task.importance = importance),
+            "due" => parse_date(value, NaiveTime::from_hms_opt(23, 59, 0).unwrap())
+                .map(|due| task.due_date = Some(due)),
+            "entry" => parse_date(value, NaiveTime::from_hms_opt(0, 0, 0).unwrap())
+                .map(|created| task.date_created = created),
+            "end" if status == "completed" => {
+                parse_date(value, NaiveTime::from_hms_opt(0, 0, 0).unwrap())
+                    .map(|done| task.date_completed = Some(done))
+            }
+            // deleted and waiting tasks also have an end, but it's not when they were done
+            "end" | "status" => Ok(()),
+            "tags" | "project" => {
+                let tags: Vec<&str> = match value {
+                    Value::Array(tags) => tags.iter().filter_map(Value::as_str).collect(),
+                    Value::String(tag) => vec![tag],
+                    _ => vec![],
+                };
+                for tag in tags {
+                    match parse_tag(&format!("+{}", tag), '+') {
+                        Some(tag) => {
+                            task.tags.insert(tag);
+                        }
+                        None => warn(format!("invalid tag '{}'", tag)),
+                    }
+                }
+                Ok(())
+            }
+            "annotations" => {
+                let notes: Vec<&str> = value
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|note| note.get("description")?.as_str())
+                    .collect();
+                if !notes.is_empty() {
+                    task.description = Some(notes.join("\n"));
+                }
+                Ok(())
+            }
+            "depends" => parse_depends(value).map(|uuids| depends = uuids),
+            // the instances of recurring tasks also have the recurrence, which is on the template
+            "recur" if fields.contains_key("parent") => Ok(()),
+            "recur" => value
+                .as_str()
+                .ok_or_else(|| format!("invalid recurrence {}", value))
+                .and_then(|recur| {
+                    recur
+                        .parse()
+                        .or_else(|_| format!("every {}", recur).parse())
+                        .map(|recurrence| task.recurrence = Some(recurrence))
+                        .map_err(|_| format!("the recurrence '{}' couldn't be mapped", recur))
+                }),
+            name if INTERNAL_FIELDS.contains(&name) => Ok(()),
+            name => {
+                *unmapped.entry(name.to_string()).or_default() += 1;
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            warn(e);
+        }
+    }
+    if status == "completed" && task.date_completed.is_none() {
+        task.date_completed = Some(task.date_created);
+    }
+
+    if task.title.is_empty() {
+        warn("the task has no description, skipped".into());
+        return None;
+    }
+    Some(Entry {
+        node: Node {
+            source: format!("Task {}", number),
+            task: Some(task),
+            id: fields
+                .get("uuid")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            parent: None,
+        },
+        depends,
+    })
+}
+
+impl TaskFormat for Taskwarrior {
+    fn import(&self, input: &str) -> Result<Import, String> {
+        let mut import = Import::default();
+        let mut unmapped = BTreeMap::new();
+        let mut entries = Vec::new();
+        for (i, value) in parse_tasks(input)?.iter().enumerate() {
+            match value.as_object() {
+                Some(fields) => entries.extend(read_task(
+                    i + 1,
+                    fields,
+                    &mut unmapped,
+                    &mut import.warnings,
+                )),
+                None => import
+                    .warnings
+                    .push(format!("Task {}: not a task object, skipped", i + 1)),
+            }
+        }
+
+        // the tasks a task depends on become its sub-tasks
+        let mut indices = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if let Some(uuid) = &entry.node.id {
+                indices.entry(uuid.clone()).or_insert(i);
+            }
+        }
+        for i in 0..entries.len() {
+            let Some(uuid) = entries[i].node.id.clone() else {
+                continue;
+            };
+            let source = entries[i].node.source.clone();
+            for dependency in std::mem::take(&mut entries[i].depends) {
+                let warning = match indices.get(&dependency).map(|&j| &mut entries[j].node) {
+                    Some(node) if node.parent.is_none() => {
+                        node.parent = Some(uuid.clone());
+                        continue;
+                    }
+                    Some(node) => format!(
+                        "{}: {} is also needed by another task, it's only a sub-task of the first one",
+                        source, node.source
+                    ),
+                    None => format!(
+                        "{}: the task it depends on ({}) isn't in the export, ignored",
+                        source, dependency
+                    ),
+                };
+                import.warnings.push(warning);
+            }
+        }
+
+        for (name, count) in unmapped {
+            import.warnings.push(format!(
+                "Field '{}' couldn't be mapped, ignored ({})",
+                name,
+                format_count(count)
+            ));
+        }
+        let nodes = entries.into_iter().map(|entry| entry.node).collect();
+        import.tasks = build_tree(nodes, &mut import.warnings);
+        Ok(import)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::count;
+    use crate::formats::tests::save_and_load;
+    use chrono::{Local, TimeZone, Utc};
+
+    #[test]
+    fn test_import() {
+        let input = r#"[
+{"id":1,"description":"Release","entry":"20261001T080000Z","due":"20261120T170000Z","priority":"H","project":"work","tags":["q4"],"status":"pending","uuid":"a","depends":["b","c","x"],"urgency":9.2},
+{"id":2,"description":"Write the changelog","status":"completed","end":"20261016T100000Z","uuid":"b","annotations":[{"entry":"20261002T080000Z","description":"Since 1.2"}]},
+{"id":3,"description":"Tag the version","priority":"U","scheduled":"20261110T000000Z","status":"pending","uuid":"c"},
+{"id":4,"description":"Announce","status":"waiting","wait":"20261121T000000Z","scheduled":"20261121T000000Z","uuid":"d","depends":"c"},
+{"description":"Old idea","status":"deleted","uuid":"e"},
+{"description":"Water plants","status":"recurring","recur":"weekly","uuid":"f"}
+]"#;
+        let import = Taskwarrior.import(input).unwrap();
+        let tasks = &import.tasks;
+        assert_eq!(tasks.len(), 2);
+
+        let release = &tasks[0];
+        assert_eq!(release.importance, 3);
+        assert_eq!(
+            release.due_date,
+            Some(
+                Utc.with_ymd_and_hms(2026, 11, 20, 17, 0, 0)
+                    .unwrap()
+                    .with_timezone(&Local)
+            )
+        );
+        assert_eq!(release.tags.iter().collect::<Vec<_>>(), vec!["q4", "work"]);
+        assert_eq!(release.sub_tasks.len(), 2);
+        let changelog = &release.sub_tasks[0];
+        assert!(changelog.is_done());
+        assert_eq!(changelog.description.as_deref(), Some("Since 1.2"));
+        assert_eq!(release.sub_tasks[1].importance, 0);
+        assert_eq!(tasks[1].title, "Announce");
+        assert!(!tasks[1].is_done());
+
+        assert_eq!(
+            import.warnings,
+            vec![
+                "Task 3: priority \"U\" isn't H, M or L, imported with importance 0",
+                "Task 5: the task was deleted, skipped",
+                "Task 6: the task repeats \"weekly\", only its instances are imported and they don't repeat",
+                "Task 1: the task it depends on (x) isn't in the export, ignored",
+                "Task 4: Task 3 is also needed by another task, it's only a sub-task of the first one",
+                "Field 'scheduled' couldn't be mapped, ignored (2 tasks)",
+                "Field 'wait' couldn't be mapped, ignored (1 task)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_lines() {
+        let input = "{\"description\":\"Call mom\",\"recur\":\"3d\",\"status\":\"pending\"},\n\
+                     {\"description\":\"Pay rent\",\"status\":\"completed\"}\n";
+        let import = Taskwarrior.import(input).unwrap();
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+        assert_eq!(import.tasks[0].recurrence.unwrap().to_string(), "every 3d");
+        assert!(import.tasks[1].is_done());
+        assert!(Taskwarrior.import("not json").is_err());
+    }
+
+    #[test]
+    fn test_import_deep() {
+        // each task depends on the next one, which would nest them 100 tasks deep
+        let input: Vec<String> = (1..=100)
+            .map(|i| {
+                format!(
+                    "{{\"description\":\"Step {}\",\"uuid\":\"{}\",\"depends\":[\"{}\"]}}",
+                    i,
+                    i,
+                    i + 1
+                )
+            })
+            .collect();
+        let import = Taskwarrior.import(&input.join("\n")).unwrap();
+        assert_eq!(
+            import.warnings,
+            vec![
+                "Task 100: the task it depends on (101) isn't in the export, ignored",
+                "Task 33: it would be nested more than 32 tasks deep under '32', imported at the top level",
+                "Task 65: it would be nested more than 32 tasks deep under '64', imported at the top level",
+                "Task 97: it would be nested more than 32 tasks deep under '96', imported at the top level",
+            ]
+        );
+        assert_eq!(import.tasks.len(), 4);
+
+        let tasks = save_and_load(import.tasks, "taskwarrior-deep");
+        assert_eq!(count(&tasks), 100);
+        assert_eq!(tasks[1].title, "Step 33");
+    }
+}
@@ -0,0 +1,265 @@
+//! The todo.txt format: one task per line, e.g.
+//! `(A) 2026-10-01 Call mom +family @phone due:2026-10-20`.
+//!
+//! Priorities (A) to (C) are importance 3 to 1, projects and contexts are tags and
+//! dates are days. Due dates are at the end of the day, like the ones entered without a time,
+//! and creation and completion dates at its start.
+//! Sub-tasks are written on their own lines with `parent:` set to the `id:` of their parent.
+
+use chrono::{Local, NaiveDate, NaiveTime};
+
+use super::{build_tree, local, Import, Node, TaskFormat};
+use crate::date::{self, DateFormat};
+use crate::task::{parse_tag, Task};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const START_OF_DAY: (u32, u32) = (0, 0);
+
+pub struct TodoTxt;
+
+fn priority(importance: u8) -> Option<char> {
+    match importance {
+        3 => Some('A'),
+        2 => Some('B'),
+        1 => Some('C'),
+        _ => None,
+    }
+}
+
+fn importance(priority: char) -> Option<u8> {
+    match priority {
+        'A' => Some(3),
+        'B' => Some(2),
+        'C' => Some(1),
+        _ => None,
+    }
+}
+
+/// Parses a priority written as `(A)`.
+fn parse_priority(word: &str) -> Option<char> {
+    let priority = word.strip_prefix('(')?.strip_suffix(')')?;
+    let mut chars = priority.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_uppercase() => Some(c),
+        _ => None,
+    }
+}
+
+/// Parses a day like `2026-10-20` at the time of day.
+fn parse_day(word: &str, (hour, min): (u32, u32)) -> Option<DateFormat> {
+    let date = NaiveDate::parse_from_str(word, DATE_FORMAT).ok()?;
+    local(date, NaiveTime::from_hms_opt(hour, min, 0)?)
+}
+
+fn format_day(date: DateFormat) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+/// Writes the task and its sub-tasks, one line each.
+fn write_task(task: &Task, parent: Option<u32>, lines: &mut Vec<String>) {
+    let mut words = Vec::new();
+    if let Some(completed) = task.date_completed {
+        words.push("x".to_string());
+        words.push(format_day(completed));
+    } else if let Some(priority) = priority(task.importance) {
+        words.push(format!("({})", priority));
+    }
+    words.push(format_day(task.date_created));
+    words.push(task.title.clone());
+    words.extend(task.tags.iter().map(|tag| format!("+{}", tag)));
+    if let Some(due) = task.due_date {
+        words.push(format!("due:{}", format_day(due)));
+    }
+    // done tasks can't have a priority, so it's kept in pri:
+    if let (Some(_), Some(priority)) = (task.date_completed, priority(task.importance)) {
+        words.push(format!("pri:{}", priority));
+    }
+    if !task.sub_tasks.is_empty() {
+        words.push(format!("id:{}", task.id));
+    }
+    if let Some(parent) = parent {
+        words.push(format!("parent:{}", parent));
+    }
+    lines.push(words.join(" "));
+
+    for sub_task in task.sub_tasks.iter() {
+        write_task(sub_task, Some(task.id), lines);
+    }
+}
+
+fn parse_line(number: usize, line: &str, warnings: &mut Vec<String>) -> Option<Node> {
+    let mut words = line.split_whitespace().peekable();
+    let mut task = Task::new(String::new(), None, 0, None);
+    let mut created = None;
+
+    if words.peek() == Some(&"x") {
+        words.next();
+        let completed = words.peek().and_then(|w| parse_day(w, START_OF_DAY));
+        if completed.is_some() {
+            words.next();
+        }
+        task.date_completed = Some(completed.unwrap_or_else(Local::now));
+    } else if let Some(priority) = words.peek().and_then(|w| parse_priority(w)) {
+        words.next();
+        task.importance = importance(priority).unwrap_or_else(|| {
+            warnings.push(format!(
+                "Line {}: priority ({}) isn't A, B or C, imported with importance 0",
+                number, priority
+            ));
+            0
+        });
+    }
+    if let Some(date) = words.peek().and_then(|w| parse_day(w, START_OF_DAY)) {
+        words.next();
+        created = Some(date);
+    }
+
+    let (mut id, mut parent) = (None, None);
+    let mut title = Vec::new();
+    for word in words {
+        if let Some(tag) = parse_tag(word, '+').or_else(|| parse_tag(word, '@')) {
+            task.tags.insert(tag);
+            continue;
+        }
+        match word.split_once(':') {
+            Some(("due", value)) => match parse_day(value, date::END_OF_DAY) {
+                Some(due) => task.due_date = Some(due),
+                None => warnings.push(format!(
+                    "Line {}: due date '{}' isn't a date like 2026-10-20, skipped",
+                    number, value
+                )),
+            },
+            Some(("pri", value)) => match value.chars().next().and_then(importance) {
+                Some(importance) => task.importance = importance,
+                None => warnings.push(format!(
+                    "Line {}: priority '{}' isn't A, B or C, imported with importance 0",
+                    number, value
+                )),
+            },
+            Some(("id", value)) => id = Some(value.to_string()),
+            Some(("parent", value)) => parent = Some(value.to_string()),
+            _ => title.push(word),
+        }
+    }
+
+    task.title = title.join(" ");
+    if task.title.is_empty() {
+        warnings.push(format!("Line {}: the task has no title, skipped", number));
+        return None;
+    }
+    if let Some(created) = created {
+        task.date_created = created;
+    }
+    Some(Node {
+        source: format!("Line {}", number),
+        task: Some(task),
+        id,
+        parent,
+    })
+}
+
+impl TaskFormat for TodoTxt {
+    fn export(&self, tasks: &[Task]) -> Result<String, String> {
+        let mut lines = Vec::new();
+        for task in tasks {
+            write_task(task, None, &mut lines);
+        }
+        Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+    }
+
+    fn import(&self, input: &str) -> Result<Import, String> {
+        let mut import = Import::default();
+        let nodes = input
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(i, line)| parse_line(i + 1, line, &mut import.warnings))
+            .collect();
+        import.tasks = build_tree(nodes, &mut import.warnings);
+        Ok(import)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::count;
+    use crate::formats::tests::{round_trip, save_and_load};
+
+    #[test]
+    fn test_import() {
+        let input = "\
+(A) 2026-10-01 Call mom +Family @phone due:2026-10-20
+x 2026-10-03 2026-10-02 Pay rent pri:B
+
+(D) Water plants id:7
+Buy soil parent:7
+2026-10-05
+Orphan parent:99
+";
+        let import = TodoTxt.import(input).unwrap();
+        let tasks = &import.tasks;
+        assert_eq!(tasks.len(), 4);
+
+        assert_eq!(tasks[0].title, "Call mom");
+        assert_eq!(tasks[0].importance, 3);
+        assert_eq!(
+            tasks[0].tags.iter().collect::<Vec<_>>(),
+            vec!["family", "phone"]
+        );
+        assert_eq!(format_day(tasks[0].date_created), "2026-10-01");
+        assert_eq!(
+            tasks[0].due_date.unwrap().naive_local().to_string(),
+            "2026-10-20 23:59:00",
+            "Due dates are at the end of the day"
+        );
+        assert_eq!(
+            tasks[0].date_created.naive_local().to_string(),
+            "2026-10-01 00:00:00"
+        );
+
+        assert_eq!(format_day(tasks[1].date_completed.unwrap()), "2026-10-03");
+        assert_eq!(format_day(tasks[1].date_created), "2026-10-02");
+        assert_eq!(tasks[1].importance, 2);
+
+        assert_eq!(tasks[2].title, "Water plants");
+        assert_eq!(tasks[2].sub_tasks[0].title, "Buy soil");
+        assert_eq!(tasks[3].title, "Orphan");
+        assert_eq!(
+            import.warnings.len(),
+            3,
+            "The priority below C, the empty task and the missing parent: {:?}",
+            import.warnings
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (exported, tasks) = round_trip(&TodoTxt, "todotxt");
+        assert_eq!(TodoTxt.export(&tasks).unwrap(), exported);
+        assert_eq!(tasks[0].sub_tasks[0].importance, 3);
+        assert_eq!(tasks[0].sub_tasks[0].sub_tasks[0].title, "Compare prices");
+    }
+
+    #[test]
+    fn test_import_malicious() {
+        let input = "\
+A due:99999999999d pri: id:1 parent:2
+B due:262144-01-01 id:2 parent:1
+99999-12-31 C parent:
+";
+        let import = TodoTxt.import(input).unwrap();
+        assert_eq!(
+            import.warnings,
+            vec![
+                "Line 1: due date '99999999999d' isn't a date like 2026-10-20, skipped",
+                "Line 1: priority '' isn't A, B or C, imported with importance 0",
+                "Line 2: due date '262144-01-01' isn't a date like 2026-10-20, skipped",
+                "Line 3: there is no parent task '', imported at the top level",
+                "Line 1: the parent task '2' forms a cycle, imported at the top level",
+            ]
+        );
+        let tasks = save_and_load(import.tasks, "todotxt-malicious");
+        assert_eq!(count(&tasks), 3);
+    }
+}
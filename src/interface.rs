@@ -1,18 +1,20 @@
 //! Contains functions for interfacing with the user.
 
-use chrono::prelude::*;
 use crate::date::DateFormat;
 
-use crate::task::{Task};
-use crate::task;
+use crate::args::Args;
 use crate::date;
+use crate::recurrence::Recurrence;
+use crate::task;
+use crate::task::Task;
 
 macro_rules! get_input {
     ($prompt:expr, $default:expr) => {{
         let mut input = String::new();
-        print!("{}", $prompt);
+        // prompts go to stderr, so they don't end up in the output (e.g. with --json)
+        eprint!("{}", $prompt);
         use std::io::Write;
-        std::io::stdout().flush().unwrap();
+        std::io::stderr().flush().unwrap();
         std::io::stdin().read_line(&mut input).unwrap();
         if input.trim().is_empty() {
             $default.to_string()
@@ -24,127 +26,269 @@ macro_rules! get_input {
 
 pub(crate) use get_input;
 
+/// Returns the width of the terminal in columns, 80 if it isn't known.
+/// The `COLUMNS` environment variable takes precedence.
+pub fn terminal_width() -> usize {
+    if let Some(columns) = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+        return columns;
+    }
+    terminal_size::terminal_size()
+        .map(|(width, _)| width.0 as usize)
+        .unwrap_or(80)
+}
+
+/// Removes the color codes from the line.
+pub fn strip_colors(line: &str) -> String {
+    let mut in_code = false;
+    line.chars()
+        .filter(|&c| {
+            in_code |= c == '\x1b';
+            let visible = !in_code;
+            in_code &= c != 'm';
+            visible
+        })
+        .collect()
+}
+
+/// Returns the number of characters of the line that are visible.
+pub fn visible_width(line: &str) -> usize {
+    strip_colors(line).chars().count()
+}
+
+/// Cuts a line with color codes to `width` visible characters, ending it with `…` if it's cut.
+pub fn truncate_to_width(line: &str, width: usize) -> String {
+    if visible_width(line) <= width {
+        return line.to_string();
+    }
+    let mut out = String::new();
+    let mut visible = 0;
+    let mut in_code = false;
+    for c in line.chars() {
+        in_code |= c == '\x1b';
+        if in_code {
+            out.push(c);
+            in_code = c != 'm';
+            continue;
+        }
+        if visible + 1 >= width {
+            break;
+        }
+        out.push(c);
+        visible += 1;
+    }
+    out + "…\x1b[0m"
+}
+
+/// Parses the importance of a task.
+pub fn parse_importance(inp: &str) -> Result<u8, String> {
+    match inp.parse::<u8>() {
+        Ok(importance) if importance <= task::IMPORTANCE_MAX => Ok(importance),
+        _ => Err(format!(
+            "Importance must be a number between 0 and {}!",
+            task::IMPORTANCE_MAX
+        )),
+    }
+}
+
 pub fn add_prompt() -> Task {
-    let mut title;
+    let mut task = Task::new(String::new(), None, 0, None);
     loop {
-        title = get_input!("Title*: ", "");
-        if title != "" {
+        let title = get_input!("Title* (+tag to add tags): ", "");
+        task.title = task.apply_tags(&title, false);
+        if !task.title.is_empty() {
             break;
         } else {
-            println!("Title cannot be empty!");
+            eprintln!("Title cannot be empty!");
         }
     }
 
     let description = get_input!("Description: ", "");
-    let description = if description == "" {
+    let description = if description.is_empty() {
         None
     } else {
         Some(description)
     };
 
-    let mut importance: u8;
+    let importance: u8;
     loop {
-        importance = get_input!("Importance: ", "0").parse().unwrap_or(69);
-        if importance > task::IMPORTANCE_MAX {
-            println!(
-                "Importance must be a number between 0 and {}!",
-                task::IMPORTANCE_MAX
-            );
-        } else {
-            break;
+        match parse_importance(&get_input!("Importance: ", "0")) {
+            Ok(i) => {
+                importance = i;
+                break;
+            }
+            Err(e) => eprintln!("{}", e),
         }
     }
 
-    let mut due: Option<DateFormat>;
+    let due: Option<DateFormat>;
     loop {
-        let inp = get_input!("Due date: ", "").to_string();
+        let inp = get_input!("Due date (e.g. tomorrow 9am): ", "").to_string();
         // allow empty due
-        if inp == "" {
+        if inp.is_empty() {
             due = None;
             break;
         }
-        let parsed_date = date::date_from_time(inp.as_str());
-        if let Ok(date) = parsed_date {
-            due = Some(date);
-            break;
+        match date::parse_date(&inp) {
+            Ok(date) => {
+                due = Some(date);
+                break;
+            }
+            Err(e) => eprintln!("{}", e),
         }
+    }
 
-        let inp = inp + ":00 " + Local::now().offset().to_string().as_str();
-
-        let mut invalid_date = false;
-        due = Some(inp.parse().unwrap_or_else(|_| {
-            println!("Invalid date! (use format: YYYY-MM-DD HH:MM or Xw Xd Xh Xm)");
-            invalid_date = true;
-            Local::now()
-        }));
-        if !invalid_date {
+    loop {
+        let inp = get_input!("Repeat (e.g. every 2w): ", "");
+        if inp.is_empty() {
             break;
         }
+        match inp.parse::<Recurrence>() {
+            Ok(recurrence) => {
+                task.recurrence = Some(recurrence);
+                break;
+            }
+            Err(e) => eprintln!("{}", e),
+        }
     }
-    let task = Task::new(title, description, importance, due);
+
+    task.description = description;
+    task.importance = importance;
+    task.due_date = due;
     task
 }
 
 pub fn edit_prompt(task: &Task) -> Task {
-    let mut edited_task = Task::new("".to_string(), None, 0, None);
-    println!(
+    let mut edited_task = task.clone();
+    eprintln!(
         "Editing task: {}\n(Press enter to leave unchanged)",
         task.title
     );
-    let title = get_input!("Title*: ", task.title);
-    if title != "" {
+    // +tag adds a tag and -tag removes it, entering only tags keeps the title
+    let title = get_input!("Title* (+tag/-tag to change tags): ", "");
+    let title = edited_task.apply_tags(&title, true);
+    if !title.is_empty() {
         edited_task.title = title;
     }
 
     let description = get_input!("Description: ", "");
-    let description = if description == "" {
-        task.description.clone()
-    } else {
-        Some(description)
-    };
-    edited_task.description = description;
+    if !description.is_empty() {
+        edited_task.description = Some(description);
+    }
 
-    let mut importance: u8;
     loop {
-        importance = get_input!("Importance: ", task.importance.to_string())
-            .parse()
-            .unwrap_or(69);
-        if importance > task::IMPORTANCE_MAX {
-            println!(
-                "Importance must be a number between 0 and {}!",
-                task::IMPORTANCE_MAX
-            );
-        } else {
-            edited_task.importance = importance;
-            break;
+        match parse_importance(&get_input!("Importance: ", task.importance.to_string())) {
+            Ok(importance) => {
+                edited_task.importance = importance;
+                break;
+            }
+            Err(e) => eprintln!("{}", e),
         }
     }
 
-    let mut due: Option<DateFormat>;
     loop {
-        let inp = get_input!("Due date: ", "").to_string();
+        let inp = get_input!("Due date (e.g. tomorrow 9am): ", "").to_string();
         // allow empty due
-        if inp == "" {
-            due = task.due_date;
+        if inp.is_empty() {
             break;
         }
-        let parsed_date = date::date_from_time(inp.as_str());
-        if let Ok(date) = parsed_date {
-            due = Some(date);
-            break;
+        match date::parse_date(&inp) {
+            Ok(date) => {
+                edited_task.due_date = Some(date);
+                break;
+            }
+            Err(e) => eprintln!("{}", e),
         }
-        let inp = inp + ":00 " + Local::now().offset().to_string().as_str();
+    }
 
-        let mut invalid_date = false;
-        due = Some(inp.parse().unwrap_or_else(|_| {
-            println!("Invalid date! (use format: YYYY-MM-DD HH:MM or Xw Xd Xh Xm)");
-            invalid_date = true;
-            Local::now()
-        }));
-        if !invalid_date {
+    loop {
+        // "never" stops the task from repeating
+        let inp = get_input!("Repeat (or never): ", "");
+        if inp.is_empty() {
+            break;
+        }
+        if inp == "never" {
+            edited_task.recurrence = None;
             break;
         }
+        match inp.parse::<Recurrence>() {
+            Ok(recurrence) => {
+                edited_task.recurrence = Some(recurrence);
+                break;
+            }
+            Err(e) => eprintln!("{}", e),
+        }
     }
-    edited_task.due_date = due;
+
     edited_task
-}
\ No newline at end of file
+}
+
+/// Creates a task from command line arguments without prompting.
+pub fn task_from_args(title: String, args: &Args) -> Result<Task, String> {
+    let mut task = Task::new(String::new(), None, 0, None);
+    task.title = task.apply_tags(&title, false);
+    if task.title.is_empty() {
+        return Err("Title cannot be empty!".into());
+    }
+    apply_args(&mut task, args)?;
+    Ok(task)
+}
+
+/// The flags that modify a task.
+pub const TASK_FLAGS: &[&str] = &[
+    "title",
+    "desc",
+    "description",
+    "importance",
+    "due",
+    "tags",
+    "repeat",
+];
+
+/// Returns true if any of the flags that modify a task is present.
+pub fn has_task_flags(args: &Args) -> bool {
+    args.has(TASK_FLAGS)
+}
+
+/// Modifies the task according to the flags in `args`.
+/// Passing an empty string to `--desc`, `--due` or `--repeat` clears the value.
+/// `--tags "+a -b"` adds and removes tags.
+pub fn apply_args(task: &mut Task, args: &Args) -> Result<(), String> {
+    if let Some(title) = args.get(&["title"]) {
+        let title = task.apply_tags(title, false);
+        if title.is_empty() {
+            return Err("Title cannot be empty!".into());
+        }
+        task.title = title;
+    }
+    if let Some(tags) = args.get(&["tags"]) {
+        let rest = task.apply_tags(tags, true);
+        if !rest.is_empty() {
+            return Err(format!("Invalid tags '{}'! (use +tag or -tag)", rest));
+        }
+    }
+    if let Some(desc) = args.get(&["desc", "description"]) {
+        task.description = if desc.is_empty() {
+            None
+        } else {
+            Some(desc.to_string())
+        };
+    }
+    if let Some(importance) = args.get(&["importance"]) {
+        task.importance = parse_importance(importance)?;
+    }
+    if let Some(due) = args.get(&["due"]) {
+        task.due_date = if due.is_empty() {
+            None
+        } else {
+            Some(date::parse_date(due).map_err(|e| e.to_string())?)
+        };
+    }
+    if let Some(recurrence) = args.get(&["repeat"]) {
+        task.recurrence = if recurrence.is_empty() {
+            None
+        } else {
+            Some(recurrence.parse()?)
+        };
+    }
+    Ok(())
+}
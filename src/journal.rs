@@ -0,0 +1,324 @@
+//! A journal of the changes made to the tasks, used for undo and redo.
+//! Every entry stores only the tasks that a command added, removed, changed or moved,
+//! as they were before and after it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::task::TaskJson;
+
+/// How many changes can be undone.
+pub const MAX_ENTRIES: usize = 50;
+
+/// A task without its sub-tasks, with where it is in the hierarchy.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+    parent: Option<u32>,
+    position: usize,
+    task: TaskJson,
+}
+
+/// How one task changed. It was added if there's nothing before and removed if there's nothing after.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Change {
+    id: u32,
+    before: Option<Record>,
+    after: Option<Record>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    /// Identifies the entry, so storages can write only the entries they don't have yet.
+    #[serde(default)]
+    pub seq: u64,
+    pub command: String,
+    pub date: String,
+    pub changes: Vec<Change>,
+}
+
+impl Entry {
+    /// Returns the tasks as they were before the change.
+    pub fn revert(&self, tasks: &[TaskJson]) -> Vec<TaskJson> {
+        apply(tasks, self.changes.iter().map(|c| (c.id, &c.before)))
+    }
+
+    /// Returns the tasks with the change made again.
+    pub fn apply(&self, tasks: &[TaskJson]) -> Vec<TaskJson> {
+        apply(tasks, self.changes.iter().map(|c| (c.id, &c.after)))
+    }
+}
+
+/// Flattens the task tree into records by id.
+fn flatten(tasks: &[TaskJson], parent: Option<u32>, records: &mut BTreeMap<u32, Record>) {
+    for (position, task) in tasks.iter().enumerate() {
+        let mut data = task.clone();
+        data.sub_tasks = Vec::new();
+        records.insert(
+            task.id,
+            Record {
+                parent,
+                position,
+                task: data,
+            },
+        );
+        flatten(&task.sub_tasks, Some(task.id), records);
+    }
+}
+
+/// Builds the task tree back from the records.
+/// Tasks whose parent is missing are put at the top level, so none are lost.
+fn unflatten(mut records: BTreeMap<u32, Record>) -> Vec<TaskJson> {
+    let mut children: HashMap<Option<u32>, Vec<(usize, u32)>> = HashMap::new();
+    for (&id, record) in records.iter() {
+        let parent = record.parent.filter(|parent| records.contains_key(parent));
+        children
+            .entry(parent)
+            .or_default()
+            .push((record.position, id));
+    }
+
+    fn build(
+        parent: Option<u32>,
+        records: &mut BTreeMap<u32, Record>,
+        children: &mut HashMap<Option<u32>, Vec<(usize, u32)>>,
+    ) -> Vec<TaskJson> {
+        let mut ids = children.remove(&parent).unwrap_or_default();
+        ids.sort();
+        ids.into_iter()
+            .filter_map(|(_, id)| {
+                let mut task = records.remove(&id)?.task;
+                task.sub_tasks = build(Some(id), records, children);
+                Some(task)
+            })
+            .collect()
+    }
+
+    let mut tasks = build(None, &mut records, &mut children);
+    // tasks that are their own ancestors can't be reached from the top level
+    while let Some(&id) = records.keys().next() {
+        let record = records.remove(&id).unwrap();
+        let mut task = record.task;
+        task.sub_tasks = build(Some(id), &mut records, &mut children);
+        tasks.push(task);
+    }
+    tasks
+}
+
+/// Returns the tasks with the records of the changed tasks replaced (or removed if there's none).
+fn apply<'a>(
+    tasks: &[TaskJson],
+    records: impl Iterator<Item = (u32, &'a Option<Record>)>,
+) -> Vec<TaskJson> {
+    let mut current = BTreeMap::new();
+    flatten(tasks, None, &mut current);
+    for (id, record) in records {
+        match record {
+            Some(record) => current.insert(id, record.clone()),
+            None => current.remove(&id),
+        };
+    }
+    unflatten(current)
+}
+
+/// Returns how the tasks changed between `before` and `after`.
+fn diff(before: &[TaskJson], after: &[TaskJson]) -> Vec<Change> {
+    let (mut old, mut new) = (BTreeMap::new(), BTreeMap::new());
+    flatten(before, None, &mut old);
+    flatten(after, None, &mut new);
+    let ids: HashSet<u32> = old.keys().chain(new.keys()).copied().collect();
+    let mut ids: Vec<u32> = ids.into_iter().collect();
+    ids.sort_unstable();
+    ids.into_iter()
+        .filter_map(|id| {
+            let (before, after) = (old.remove(&id), new.remove(&id));
+            (before != after).then_some(Change { id, before, after })
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Journal {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+    #[serde(default)]
+    next_seq: u64,
+}
+
+impl Journal {
+    /// Returns the path of the journal kept next to the save file.
+    pub fn path_for(save_file: &Path) -> PathBuf {
+        let mut name = save_file.file_stem().unwrap_or_default().to_os_string();
+        name.push(".journal.json");
+        save_file.with_file_name(name)
+    }
+
+    pub fn load(path: &Path) -> Journal {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return Journal::default(),
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            eprintln!("Could not parse undo journal, starting a new one: {}", e);
+            Journal::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string(self).unwrap();
+        crate::backup::write_atomic(path, json.as_bytes())
+            .map_err(|e| format!("Could not write undo journal: {}", e))
+    }
+
+    /// Builds the journal from its entries, oldest first. The bool is true for redo entries.
+    #[cfg(feature = "sqlite")]
+    pub fn from_entries(entries: Vec<(bool, Entry)>) -> Journal {
+        let mut journal = Journal::default();
+        for (redo, entry) in entries {
+            journal.next_seq = journal.next_seq.max(entry.seq + 1);
+            if redo {
+                journal.redo.push(entry);
+            } else {
+                journal.undo.push(entry);
+            }
+        }
+        journal
+    }
+
+    /// Returns the entries, oldest first. The bool is true for redo entries.
+    #[cfg(feature = "sqlite")]
+    pub fn entries(&self) -> impl Iterator<Item = (bool, &Entry)> {
+        let undo = self.undo.iter().map(|entry| (false, entry));
+        undo.chain(self.redo.iter().map(|entry| (true, entry)))
+    }
+
+    /// Records a change made by `command`, from the tasks `before` it to the ones `after` it.
+    pub fn record(&mut self, command: String, before: &[TaskJson], after: &[TaskJson]) {
+        let entry = Entry {
+            seq: 0,
+            command,
+            date: chrono::Local::now().to_string(),
+            changes: diff(before, after),
+        };
+        self.push(false, entry);
+        self.redo.clear();
+    }
+
+    /// Takes the last change off the journal and returns it, so that it can be reverted.
+    pub fn undo(&mut self) -> Option<Entry> {
+        let entry = self.undo.pop()?;
+        self.push(true, entry.clone());
+        Some(entry)
+    }
+
+    /// Takes the last undone change and returns it, so that it can be applied again.
+    pub fn redo(&mut self) -> Option<Entry> {
+        let entry = self.redo.pop()?;
+        self.push(false, entry.clone());
+        Some(entry)
+    }
+
+    /// Adds the entry to the undo or redo list with a new seq, since storages can't tell
+    /// that an entry moved between them otherwise.
+    fn push(&mut self, redo: bool, mut entry: Entry) {
+        entry.seq = self.next_seq;
+        self.next_seq += 1;
+        let entries = if redo { &mut self.redo } else { &mut self.undo };
+        entries.push(entry);
+        if entries.len() > MAX_ENTRIES {
+            entries.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use crate::task::{Task, TaskList};
+    use chrono::{Local, TimeZone};
+
+    fn titles(tasks: Vec<TaskJson>) -> Vec<String> {
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        task_list.restore(tasks);
+        task_list
+            .flatten()
+            .iter()
+            .map(|(path, task)| format!("{} {}", crate::task::format_path(path), task.title))
+            .collect()
+    }
+
+    fn snapshot(titles: &[&str]) -> Vec<TaskJson> {
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        for title in titles {
+            let mut task = Task::new(title.to_string(), None, 0, None);
+            task.date_created = Local.timestamp_opt(0, 0).unwrap();
+            task_list.add_task(task);
+        }
+        task_list.snapshot()
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut journal = Journal::default();
+        journal.record("add a".into(), &snapshot(&[]), &snapshot(&["a"]));
+        journal.record("add b".into(), &snapshot(&["a"]), &snapshot(&["a", "b"]));
+        assert_eq!(journal.undo.last().unwrap().changes.len(), 1);
+
+        let entry = journal.undo().unwrap();
+        assert_eq!(entry.command, "add b");
+        assert_eq!(titles(entry.revert(&snapshot(&["a", "b"]))), ["1 a"]);
+
+        let entry = journal.redo().unwrap();
+        assert_eq!(titles(entry.apply(&snapshot(&["a"]))), ["1 a", "2 b"]);
+        assert!(journal.redo().is_none());
+
+        journal.undo();
+        journal.record("add c".into(), &snapshot(&["a"]), &snapshot(&["a", "c"]));
+        assert!(
+            journal.redo().is_none(),
+            "A new change should clear the redo history"
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        for title in ["a", "b", "c"] {
+            task_list.add_task(Task::new(title.into(), None, 0, None));
+        }
+        task_list
+            .insert(&[3], Task::new("d".into(), None, 0, None))
+            .unwrap();
+        let before = task_list.snapshot();
+
+        // moves c with its sub-task under a and edits b
+        let c = task_list.remove(&[3]).unwrap();
+        task_list.insert(&[1], c).unwrap();
+        task_list.get_mut(&[2]).unwrap().title = "B".into();
+        let after = task_list.snapshot();
+
+        let changes = diff(&before, &after);
+        assert_eq!(
+            changes.iter().map(|change| change.id).collect::<Vec<_>>(),
+            [2, 3],
+            "The sub-task d didn't change"
+        );
+        let entry = Entry {
+            seq: 0,
+            command: "move".into(),
+            date: String::new(),
+            changes,
+        };
+        assert_eq!(entry.revert(&after), before);
+        assert_eq!(entry.apply(&before), after);
+    }
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(
+            Journal::path_for(Path::new("/data/stuff/saved_stuff.json")),
+            PathBuf::from("/data/stuff/saved_stuff.journal.json")
+        );
+    }
+}
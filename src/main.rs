@@ -2,103 +2,229 @@
 //! The tasks are printed nicely, in color to the terminal.
 //! Tasks can contain sub tasks.
 //! You can save the tasks to a file.
+mod agenda;
+mod args;
+mod backup;
+mod calendar;
 mod commands;
+mod config;
 mod date;
+mod filter;
+mod formats;
 mod interface;
+mod journal;
+mod matrix;
+mod output;
+mod paths;
+mod recurrence;
+mod schema;
+mod search;
+mod storage;
 mod task;
+mod tree;
 
-use interface::{add_prompt, get_input};
+use args::{split_words, Args};
+use config::Config;
+use interface::get_input;
+use output::{Format, Output};
 use task::TaskList;
 
-fn run_prompt(task_list: &mut TaskList) {
-    'outer: loop {
-        let input = get_input!("\n> ", "").to_lowercase();
-        let mut input = input.split_whitespace();
-        let command = input.next().unwrap_or("");
-        let arg = input.next().unwrap_or("");
+/// Runs the command with the specified keyword and saves the tasks if it changed them.
+/// With `--help` only the help of the command is printed, other flags it doesn't take are an error.
+/// Changes made by the command are recorded in the journal so they can be undone.
+/// The output is printed in the specified format once the changes are saved.
+fn run_command(
+    command: &str,
+    args: &Args,
+    task_list: &mut TaskList,
+    format: Format,
+) -> Result<(), String> {
+    let c =
+        commands::find_command(command).ok_or_else(|| format!("Unknown command: {}", command))?;
+    if args.has(&["help"]) {
+        println!("{}", c.help());
+        return Ok(());
+    }
+    args.check_flags(c.flags())?;
+    let before = task_list.snapshot();
+    let output = c.execute(args, task_list)?;
 
-        if command == "" {
-            continue;
+    // only commands that changed something need to be saved
+    let after = task_list.snapshot();
+    if after != before {
+        if c.journaled() {
+            let command = format!("{} {}", command, args).trim().to_string();
+            task_list.journal.record(command, &before, &after);
         }
+        task_list.save()?;
+    }
+    print!("{}", output.render(format));
+    Ok(())
+}
 
-        let command_list = commands::Commands::new();
-        for c in command_list {
-            if c.keywords().contains(&command) {
-                match c.execute(arg, task_list) {
-                    Ok(msg) => {
-                        if msg != "" {
-                            println!("\n\x1b[2;37;37m{}\x1b[0m", msg);
-                        }
-                        task_list.save_to_file()
-                    }
-                    Err(e) => println!("{}", e),
-                }
-                continue 'outer;
+fn print_help() {
+    for c in commands::Commands::new() {
+        println!("{}", c.help());
+    }
+}
+
+fn run_prompt(task_list: &mut TaskList, format: Format) {
+    loop {
+        let input = get_input!("\n> ", "");
+        let words = match split_words(&input) {
+            Ok(words) => words,
+            Err(e) => {
+                println!("{}", e);
+                continue;
             }
+        };
+        let command = if let Some(command) = words.first() {
+            command.to_lowercase()
+        } else {
+            continue;
+        };
+
+        if command == "help" || command == "h" {
+            print_help();
+            continue;
         }
 
-        println!("Unknown command: {}", command);
+        // the format can also be changed for a single command
+        let mut format = format;
+        let result = Args::parse(&words[1..]).and_then(|mut args| {
+            format = Format::take(&mut args)?.unwrap_or(format);
+            run_command(&command, &args, task_list, format)
+        });
+        if let Err(e) = result {
+            println!("{}", format.error(&e));
+        }
     }
 }
 
-fn main() {
-    let mut task_list = TaskList::new();
-    task_list.load_from_file();
+/// Shows one important task, one urgent and a random one.
+fn show_summary(task_list: &TaskList) -> Output {
+    if task_list.pending_tasks().is_empty() {
+        return Output::default();
+    }
+
+    let max_priority = task_list.sorted_by_importance();
+    let tasks = task_list.get_by_importance(max_priority[0].importance);
+    let important = TaskList::get_random(&tasks);
+    let urgent = task_list.most_urgent();
+    let random = task_list.random_task().unwrap();
 
-    let args = std::env::args().collect::<Vec<String>>();
-    match args.len() {
-        1 => {
-            task_list.print_tasks();
-            run_prompt(&mut task_list);
+    let mut text = String::new();
+    let mut data = serde_json::Map::new();
+    let mut add_section = |name: &str, key: &str, path: &[usize], task: &task::Task| {
+        text += &format!("\x1b[1;4;37m{}\x1b[0m:\n{}\n\n", name, task.header(&[]));
+        data.insert(key.to_string(), task.to_json(path, false));
+    };
+    let path = |task: &task::Task| task_list.find_id(task.id).unwrap_or_default();
+    add_section(
+        "High priority",
+        "high_priority",
+        &path(&important),
+        &important,
+    );
+    if let Some((urgent_path, task)) = &urgent {
+        add_section("Urgent", "urgent", urgent_path, task);
+    }
+    add_section("Random", "random", &path(&random), &random);
+    Output::new(text, data.into())
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let mut args = match Args::parse(&args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
-        2 => match args[1].as_str() {
-            "show" | "s" => {
-                if task_list.tasks.is_empty() {
-                    return;
-                }
+    };
 
-                if args.len() > 2 && args[2] == "all" {
-                    task_list.print_tasks();
-                    return;
-                }
+    let file_path = match paths::save_file_path(args.take("file").as_deref()) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let storage = match storage::open(file_path) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut task_list = TaskList::new(storage);
+    if let Some(path) = paths::config_file_path() {
+        task_list.config = Config::load(&path);
+    }
+    task_list.load();
 
-                let max_priority = task_list.sorted_by_importance();
-                let tasks = task_list.get_by_importance(max_priority[0].importance);
-                let task = TaskList::get_random(&tasks);
-                println!("\x1b[1;4;37mHigh priority\x1b[0m:");
-                task.print_header();
-                println!("\n");
+    let format = match Format::take(&mut args) {
+        Ok(format) => format.unwrap_or(Format::Plain),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    // only where the tasks are shown, so it doesn't end up in e.g. exported files
+    let print_no_tasks = |task_list: &TaskList| {
+        if task_list.tasks.is_empty() && format == Format::Plain {
+            println!("You have no tasks!");
+        }
+    };
 
-                let least_due = task_list.sorted_by_due();
-                let task = &least_due[0];
-                println!("\x1b[1;4;37mUrgent\x1b[0m:");
-                task.print_header();
-                println!("\n");
+    if args.positional.is_empty() {
+        if let Err(e) = args.check_flags(&[]) {
+            eprintln!("{}", format.error(&e));
+            std::process::exit(1);
+        }
+        if args.has(&["help"]) {
+            println!(
+                "Usage: stuff [command] [args] [--flags] [--json | --format json|ndjson|plain]\n"
+            );
+            print_help();
+            return;
+        }
+        print_no_tasks(&task_list);
+        let output = output::task_list(&task_list.pending_roots(), true);
+        print!("{}", output.render(format));
+        run_prompt(&mut task_list, format);
+        return;
+    }
+    let command = args.positional.remove(0).to_lowercase();
+    // `show` without a task is handled below, run_command checks the flags of the other commands
+    if let Some(c) = commands::find_command(&command) {
+        if let Err(e) = args.check_flags(c.flags()) {
+            eprintln!("{}", format.error(&e));
+            std::process::exit(1);
+        }
+    }
 
-                let task = task_list.random_task().unwrap();
-                println!("\x1b[1;4;37mRandom\x1b[0m:");
-                task.print_header();
-                println!("\n");
-            }
-            "add" | "a" => {
-                let task = add_prompt();
-                task_list.add_task(task);
-                task_list.save_to_file();
-            }
-            "random" | "rand" | "r" => {
-                if let Some(task) = task_list.random_task() {
-                    println!("Random Task:");
-                    task.print_task();
-                } else {
-                    println!("You have no tasks!");
-                }
-            }
-            _ => {
-                println!("Unknown command: {}", args[1]);
-            }
-        },
+    match command.as_str() {
+        "help" | "h" | "-h" => {
+            println!(
+                "Usage: stuff [command] [args] [--flags] [--json | --format json|ndjson|plain]\n"
+            );
+            print_help();
+        }
+        "show" | "s" if args.positional.is_empty() && !args.has(&["tree", "depth", "help"]) => {
+            print_no_tasks(&task_list);
+            print!("{}", show_summary(&task_list).render(format))
+        }
+        "show" | "s" if args.arg() == "all" && !args.has(&["help"]) => {
+            print_no_tasks(&task_list);
+            let output = output::task_list(&task_list.pending_roots(), true);
+            print!("{}", output.render(format));
+        }
         _ => {
-            println!("Usage: {} [option]", args[0]);
+            if let Err(e) = run_command(&command, &args, &mut task_list, format) {
+                eprintln!("{}", format.error(&e));
+                std::process::exit(1);
+            }
         }
     }
 }
@@ -0,0 +1,188 @@
+//! The Eisenhower matrix: tasks split into four quadrants by importance and urgency.
+
+use chrono::Local;
+
+use crate::config::MatrixThresholds;
+use crate::date::{get_time_left, DateFormat};
+use crate::task::{format_path, Task};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quadrant {
+    /// Important and urgent.
+    Do,
+    /// Important but not urgent.
+    Schedule,
+    /// Urgent but not important.
+    Delegate,
+    /// Neither important nor urgent.
+    Eliminate,
+}
+
+impl Quadrant {
+    pub const ALL: [Quadrant; 4] = [
+        Quadrant::Do,
+        Quadrant::Schedule,
+        Quadrant::Delegate,
+        Quadrant::Eliminate,
+    ];
+
+    pub fn of(task: &Task, thresholds: &MatrixThresholds, now: DateFormat) -> Quadrant {
+        let important = task.importance >= thresholds.importance;
+        let urgent = task.due_date.is_some_and(|due| {
+            (due - now).num_minutes() as f64 <= thresholds.due_days * 24.0 * 60.0
+        });
+        match (important, urgent) {
+            (true, true) => Quadrant::Do,
+            (true, false) => Quadrant::Schedule,
+            (false, true) => Quadrant::Delegate,
+            (false, false) => Quadrant::Eliminate,
+        }
+    }
+
+    /// The name used in the JSON output.
+    pub fn name(&self) -> &str {
+        match self {
+            Quadrant::Do => "do",
+            Quadrant::Schedule => "schedule",
+            Quadrant::Delegate => "delegate",
+            Quadrant::Eliminate => "eliminate",
+        }
+    }
+
+    fn title(&self) -> &str {
+        match self {
+            Quadrant::Do => "Do (important, urgent)",
+            Quadrant::Schedule => "Schedule (important)",
+            Quadrant::Delegate => "Delegate (urgent)",
+            Quadrant::Eliminate => "Eliminate (neither)",
+        }
+    }
+
+    fn color(&self) -> &str {
+        match self {
+            Quadrant::Do => "\x1b[1;37;31m",
+            Quadrant::Schedule => "\x1b[1;37;33m",
+            Quadrant::Delegate => "\x1b[1;37;36m",
+            Quadrant::Eliminate => "\x1b[1;37;37m",
+        }
+    }
+}
+
+/// Cuts the text to `width` characters and pads it with spaces.
+fn fit(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len > width {
+        let cut: String = text.chars().take(width.saturating_sub(1)).collect();
+        format!("{}…", cut)
+    } else {
+        format!("{}{}", text, " ".repeat(width - len))
+    }
+}
+
+/// Returns the lines of one box: its title and a line for every task.
+fn box_lines(quadrant: Quadrant, tasks: &[(Vec<usize>, &Task)], width: usize) -> Vec<String> {
+    let title = format!("{} ({})", quadrant.title(), tasks.len());
+    let mut lines = vec![format!("{}{}\x1b[0m", quadrant.color(), fit(&title, width))];
+    for (path, task) in tasks {
+        let mut line = format!("{}: {}", format_path(path), task.title);
+        if let Some(due_date) = task.due_date {
+            line += &format!(" [due in {}]", get_time_left(due_date));
+        }
+        lines.push(fit(&line, width));
+    }
+    lines
+}
+
+/// Renders the tasks of each quadrant (in the order of `Quadrant::ALL`) as four boxes
+/// that fit in `width` columns.
+pub fn render(quadrants: &[Vec<(Vec<usize>, &Task)>; 4], width: usize) -> Vec<String> {
+    // two borders and a space on each side of both boxes, and the border in the middle
+    let cell = width.saturating_sub(7).max(20) / 2;
+    let line = "─".repeat(cell + 2);
+    let boxes: Vec<Vec<String>> = Quadrant::ALL
+        .iter()
+        .zip(quadrants.iter())
+        .map(|(quadrant, tasks)| box_lines(*quadrant, tasks, cell))
+        .collect();
+
+    let mut out = vec![format!("┌{}┬{}┐", line, line)];
+    for (row, (left, right)) in [(&boxes[0], &boxes[1]), (&boxes[2], &boxes[3])]
+        .into_iter()
+        .enumerate()
+    {
+        if row == 1 {
+            out.push(format!("├{}┼{}┤", line, line));
+        }
+        let empty = " ".repeat(cell);
+        for i in 0..left.len().max(right.len()) {
+            out.push(format!(
+                "│ {} │ {} │",
+                left.get(i).unwrap_or(&empty),
+                right.get(i).unwrap_or(&empty)
+            ));
+        }
+    }
+    out.push(format!("└{}┴{}┘", line, line));
+    out
+}
+
+/// Splits the tasks that aren't done into the quadrants, in the order of `Quadrant::ALL`.
+pub fn classify<'a>(
+    tasks: Vec<(Vec<usize>, &'a Task)>,
+    thresholds: &MatrixThresholds,
+) -> [Vec<(Vec<usize>, &'a Task)>; 4] {
+    let now = Local::now();
+    let mut quadrants: [Vec<(Vec<usize>, &Task)>; 4] = Default::default();
+    for (path, task) in tasks {
+        if task.is_done() {
+            continue;
+        }
+        let quadrant = Quadrant::of(task, thresholds, now);
+        let index = Quadrant::ALL.iter().position(|q| *q == quadrant).unwrap();
+        quadrants[index].push((path, task));
+    }
+    quadrants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::visible_width;
+    use chrono::Duration;
+
+    #[test]
+    fn test_quadrants() {
+        let thresholds = MatrixThresholds::default();
+        let now = Local::now();
+        let quadrant = |importance, due_in_days: Option<i64>| {
+            let due = due_in_days.map(|days| now + Duration::days(days));
+            Quadrant::of(
+                &Task::new("".into(), None, importance, due),
+                &thresholds,
+                now,
+            )
+        };
+
+        assert_eq!(quadrant(3, Some(1)), Quadrant::Do);
+        assert_eq!(
+            quadrant(2, Some(-3)),
+            Quadrant::Do,
+            "Overdue tasks are urgent"
+        );
+        assert_eq!(quadrant(3, Some(7)), Quadrant::Schedule);
+        assert_eq!(quadrant(2, None), Quadrant::Schedule);
+        assert_eq!(quadrant(1, Some(2)), Quadrant::Delegate);
+        assert_eq!(quadrant(0, None), Quadrant::Eliminate);
+    }
+
+    #[test]
+    fn test_render() {
+        let task = Task::new("A very long title that doesn't fit".into(), None, 3, None);
+        let quadrants = [vec![(vec![1], &task)], vec![], vec![], vec![]];
+        let lines = render(&quadrants, 60);
+
+        // the width without the color codes
+        assert!(lines.iter().all(|line| visible_width(line) == 59));
+        assert!(lines.iter().any(|line| line.contains("1: A very long")));
+    }
+}
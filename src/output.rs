@@ -0,0 +1,184 @@
+//! The results of commands and the formats they are printed in.
+//! Commands return both a colored text for people and structured data for scripts,
+//! which is printed as JSON with `--json` or `--format json|ndjson|plain`.
+
+use serde_json::{json, Value};
+
+use crate::args::Args;
+use crate::task::{format_path, Task};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Colored text.
+    Plain,
+    /// One JSON object with the message and the data.
+    Json,
+    /// One JSON value per line, one for every item of the data.
+    Ndjson,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s.to_lowercase().as_str() {
+            "plain" | "text" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "ndjson" | "jsonl" => Ok(Format::Ndjson),
+            _ => Err(format!(
+                "Invalid format '{}'! (use json, ndjson or plain)",
+                s
+            )),
+        }
+    }
+}
+
+impl Format {
+    /// Removes the `--json` and `--format` flags from the arguments and returns the format they ask for.
+    pub fn take(args: &mut Args) -> Result<Option<Format>, String> {
+        let json = args.take("json").is_some();
+        match args.take("format") {
+            Some(format) => format.parse().map(Some),
+            None if json => Ok(Some(Format::Json)),
+            None => Ok(None),
+        }
+    }
+
+    /// Formats an error so that scripts can tell it apart from the output.
+    pub fn error(&self, e: &str) -> String {
+        match self {
+            Format::Plain => e.to_string(),
+            Format::Json | Format::Ndjson => json!({ "error": e }).to_string(),
+        }
+    }
+}
+
+/// What a command prints: the text, a short message after it and the same information as data.
+#[derive(Debug, Default, PartialEq)]
+pub struct Output {
+    pub message: String,
+    pub text: String,
+    pub data: Value,
+}
+
+impl Output {
+    pub fn new(text: String, data: Value) -> Output {
+        Output {
+            message: String::new(),
+            text,
+            data,
+        }
+    }
+
+    /// An output with only a message.
+    pub fn message(message: impl Into<String>) -> Output {
+        Output {
+            message: message.into(),
+            ..Output::default()
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Output {
+        self.message = message.into();
+        self
+    }
+
+    pub fn with_data(mut self, data: Value) -> Output {
+        self.data = data;
+        self
+    }
+
+    /// Returns the output as it is printed in the format.
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Plain => {
+                let mut out = self.text.clone();
+                if !self.message.is_empty() {
+                    out += &format!("\n\x1b[2;37;37m{}\x1b[0m\n", self.message);
+                }
+                out
+            }
+            Format::Json => {
+                let output = json!({ "message": self.message, "data": self.data });
+                format!(
+                    "{}\n",
+                    serde_json::to_string_pretty(&output).unwrap_or_default()
+                )
+            }
+            Format::Ndjson => {
+                let lines = match &self.data {
+                    Value::Array(items) => items.clone(),
+                    Value::Null if self.message.is_empty() => Vec::new(),
+                    Value::Null => vec![json!({ "message": self.message })],
+                    data => vec![data.clone()],
+                };
+                lines.iter().map(|line| format!("{}\n", line)).collect()
+            }
+        }
+    }
+}
+
+/// Returns the output for a list of tasks: a line with the path and header of each task.
+/// If `count_sub_tasks` is set, the number of sub-tasks is shown after each header.
+pub fn task_list(tasks: &[(Vec<usize>, &Task)], count_sub_tasks: bool) -> Output {
+    let mut text = String::new();
+    for (path, task) in tasks {
+        text += &format!("{}: {}", format_path(path), task.header(&[]));
+        if count_sub_tasks && !task.sub_tasks.is_empty() {
+            text += &format!(" \x1b[2;37;37m(+{})\x1b[0m", task.sub_tasks.len());
+        }
+        text += "\n";
+    }
+    let data = tasks
+        .iter()
+        .map(|(path, task)| task.to_json(path, false))
+        .collect();
+    Output::new(text, data)
+}
+
+/// Adds a field to a JSON object.
+pub fn with_field(mut json: Value, name: &str, value: impl Into<Value>) -> Value {
+    if let Some(object) = json.as_object_mut() {
+        object.insert(name.to_string(), value.into());
+    }
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_format() {
+        let mut args = Args::parse(&["2", "--json"]).unwrap();
+        assert_eq!(Format::take(&mut args), Ok(Some(Format::Json)));
+        assert_eq!(args, Args::parse(&["2"]).unwrap());
+
+        let mut args = Args::parse(&["--format", "ndjson"]).unwrap();
+        assert_eq!(Format::take(&mut args), Ok(Some(Format::Ndjson)));
+        let mut args = Args::parse(&["--format=xml"]).unwrap();
+        assert!(Format::take(&mut args).is_err());
+        assert_eq!(Format::take(&mut Args::default()), Ok(None));
+    }
+
+    #[test]
+    fn test_render() {
+        let output = Output::new("1: a\n2: b\n".into(), json!([{ "id": 1 }, { "id": 2 }]))
+            .with_message("Tasks found: 2");
+        assert_eq!(
+            output.render(Format::Plain),
+            "1: a\n2: b\n\n\x1b[2;37;37mTasks found: 2\x1b[0m\n"
+        );
+        assert_eq!(output.render(Format::Ndjson), "{\"id\":1}\n{\"id\":2}\n");
+
+        let json: Value = serde_json::from_str(&output.render(Format::Json)).unwrap();
+        assert_eq!(json["message"], "Tasks found: 2");
+        assert_eq!(json["data"][1]["id"], 2);
+
+        assert_eq!(
+            Output::message("Task edited").render(Format::Ndjson),
+            "{\"message\":\"Task edited\"}\n"
+        );
+        assert_eq!(Output::default().render(Format::Ndjson), "");
+    }
+}
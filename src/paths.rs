@@ -0,0 +1,101 @@
+//! Resolving where the tasks are saved.
+
+use std::path::{Path, PathBuf};
+
+pub const SAVE_FILE_NAME: &str = "saved_stuff.json";
+pub const DATA_DIR_NAME: &str = "stuff";
+pub const FILE_ENV_VAR: &str = "STUFF_FILE";
+pub const CONFIG_FILE_NAME: &str = "config.json";
+pub const CONFIG_ENV_VAR: &str = "STUFF_CONFIG";
+
+/// Returns the path of the save file.
+/// In order of priority: the `--file` flag, the `STUFF_FILE` environment variable,
+/// `$XDG_DATA_HOME/stuff/saved_stuff.json` and `~/.local/share/stuff/saved_stuff.json`.
+pub fn save_file_path(file_flag: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(file) = file_flag {
+        return Ok(PathBuf::from(file));
+    }
+    if let Some(file) = env_path(FILE_ENV_VAR) {
+        return Ok(file);
+    }
+
+    let path = default_save_file_path()?;
+    migrate_legacy_save_file(&path);
+    Ok(path)
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    match std::env::var_os(name) {
+        Some(value) if !value.is_empty() => Some(PathBuf::from(value)),
+        _ => None,
+    }
+}
+
+fn default_save_file_path() -> Result<PathBuf, String> {
+    // relative paths in XDG variables are invalid and should be ignored
+    let data_dir = match env_path("XDG_DATA_HOME") {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => {
+            let home = env_path("HOME").ok_or("Couldn't find the home directory")?;
+            home.join(".local").join("share")
+        }
+    };
+    Ok(data_dir.join(DATA_DIR_NAME).join(SAVE_FILE_NAME))
+}
+
+/// Returns the path of the config file.
+/// In order of priority: the `STUFF_CONFIG` environment variable,
+/// `$XDG_CONFIG_HOME/stuff/config.json` and `~/.config/stuff/config.json`.
+pub fn config_file_path() -> Option<PathBuf> {
+    if let Some(file) = env_path(CONFIG_ENV_VAR) {
+        return Some(file);
+    }
+    let config_dir = match env_path("XDG_CONFIG_HOME") {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => env_path("HOME")?.join(".config"),
+    };
+    Some(config_dir.join(DATA_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+/// Older versions kept the save file next to the executable.
+fn legacy_save_file_path() -> Option<PathBuf> {
+    let mut file_path = std::env::current_exe().ok()?;
+    file_path.pop();
+    file_path.push(SAVE_FILE_NAME);
+    Some(file_path)
+}
+
+/// Moves the save file from next to the executable to `path` if there isn't one there yet.
+fn migrate_legacy_save_file(path: &Path) {
+    if path.exists() {
+        return;
+    }
+    let legacy_path = match legacy_save_file_path() {
+        Some(legacy_path) if legacy_path.is_file() => legacy_path,
+        _ => return,
+    };
+
+    if let Some(dir) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("Could not create directory {}: {}", dir.display(), e);
+            return;
+        }
+    }
+    // renaming doesn't work across file systems so fall back to copying
+    let moved = std::fs::rename(&legacy_path, path).is_ok()
+        || (std::fs::copy(&legacy_path, path).is_ok()
+            && std::fs::remove_file(&legacy_path).is_ok());
+    if moved {
+        eprintln!(
+            "Moved save file from {} to {}",
+            legacy_path.display(),
+            path.display()
+        );
+    } else {
+        eprintln!(
+            "Could not move save file from {} to {}",
+            legacy_path.display(),
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Recurrence rules for tasks that repeat.
+//! When a recurring task is done, a new one is created with the next due date.
+
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::date::{parse_weekday, weekday_name, DateFormat};
+
+/// The largest interval of a recurrence, e.g. "every 9999d".
+pub const MAX_INTERVAL: u32 = 9999;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recurrence {
+    /// Every n units after the previous due date, e.g. "every 2w".
+    Every(u32, Unit),
+    /// On every given day of the week, e.g. "every monday".
+    Weekday(Weekday),
+    /// On the given day of every n months, e.g. "monthly on the 1st" or "every 2mo on the 31st".
+    MonthlyOn(u32, u32),
+    /// n units after the task was done, e.g. "3d after completion".
+    AfterCompletion(u32, Unit),
+}
+
+impl Unit {
+    fn parse(unit: &str) -> Option<Unit> {
+        match unit {
+            "h" | "hour" | "hours" => Some(Unit::Hour),
+            "d" | "day" | "days" => Some(Unit::Day),
+            "w" | "week" | "weeks" => Some(Unit::Week),
+            "mo" | "month" | "months" => Some(Unit::Month),
+            "y" | "year" | "years" => Some(Unit::Year),
+            _ => None,
+        }
+    }
+
+    fn symbol(&self) -> &str {
+        match self {
+            Unit::Hour => "h",
+            Unit::Day => "d",
+            Unit::Week => "w",
+            Unit::Month => "mo",
+            Unit::Year => "y",
+        }
+    }
+
+    /// Returns the date `n` units later, or None if it's out of the supported range.
+    fn add(&self, date: DateFormat, n: u32) -> Option<DateFormat> {
+        match self {
+            Unit::Hour => date.checked_add_signed(Duration::hours(n as i64)),
+            Unit::Day => date.checked_add_signed(Duration::days(n as i64)),
+            Unit::Week => date.checked_add_signed(Duration::weeks(n as i64)),
+            Unit::Month => add_months(date, n as i64),
+            Unit::Year => add_months(date, n as i64 * 12),
+        }
+    }
+}
+
+/// Returns the date in the month `n` months later, using its last day if it's shorter.
+fn add_months(date: DateFormat, n: i64) -> Option<DateFormat> {
+    let months = date.year() as i64 * 12 + date.month0() as i64 + n;
+    let year = i32::try_from(months.div_euclid(12)).ok()?;
+    let day = day_in_month(year, months.rem_euclid(12) as u32 + 1, date.day())?;
+    at_time_of(day, date)
+}
+
+/// Returns the day of the month, or the last day of the month if it's shorter.
+/// None if the year is out of the supported range.
+fn day_in_month(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    (1..=day)
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+}
+
+/// Parses an interval like "2w", "2 weeks", "week" or "3mo".
+fn parse_interval(words: &[&str]) -> Option<(u32, Unit)> {
+    match words {
+        [unit] => {
+            let split = unit.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+            let (n, unit) = unit.split_at(split);
+            let n = if n.is_empty() { 1 } else { n.parse().ok()? };
+            Some((n, Unit::parse(unit)?)).filter(|(n, _)| (1..=MAX_INTERVAL).contains(n))
+        }
+        [n, unit] => Some((n.parse().ok()?, Unit::parse(unit)?))
+            .filter(|(n, _)| (1..=MAX_INTERVAL).contains(n)),
+        _ => None,
+    }
+}
+
+/// Parses "1st", "2nd", "15th" or "15".
+fn parse_day_of_month(day: &str) -> Option<u32> {
+    let day = day.trim_end_matches(|c: char| c.is_alphabetic());
+    day.parse().ok().filter(|d| (1..=31).contains(d))
+}
+
+fn ordinal(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+impl std::str::FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Recurrence, String> {
+        let s = s.trim().to_lowercase();
+        let words: Vec<&str> = s.split_whitespace().collect();
+
+        let recurrence = match words.as_slice() {
+            ["daily"] => Some(Recurrence::Every(1, Unit::Day)),
+            ["weekly"] => Some(Recurrence::Every(1, Unit::Week)),
+            ["monthly"] => Some(Recurrence::Every(1, Unit::Month)),
+            ["yearly"] => Some(Recurrence::Every(1, Unit::Year)),
+            ["monthly", "on", "the", day] | ["monthly", "on", day] => {
+                parse_day_of_month(day).map(|day| Recurrence::MonthlyOn(1, day))
+            }
+            ["every", interval @ .., "on", "the", day] | ["every", interval @ .., "on", day] => {
+                match (parse_interval(interval), parse_day_of_month(day)) {
+                    (Some((n, Unit::Month)), Some(day)) => Some(Recurrence::MonthlyOn(n, day)),
+                    _ => None,
+                }
+            }
+            ["every", day] if parse_weekday(day).is_some() => {
+                parse_weekday(day).map(Recurrence::Weekday)
+            }
+            [interval @ .., "after", "completion"] => {
+                let interval = interval.strip_prefix(&["every"]).unwrap_or(interval);
+                parse_interval(interval).map(|(n, unit)| Recurrence::AfterCompletion(n, unit))
+            }
+            ["every", interval @ ..] => {
+                parse_interval(interval).map(|(n, unit)| Recurrence::Every(n, unit))
+            }
+            _ => None,
+        };
+
+        recurrence.ok_or_else(|| {
+            format!(
+                "Invalid recurrence '{}'! (e.g. every 2w, every monday, monthly on the 1st, 3d after completion; intervals are at most {})",
+                s, MAX_INTERVAL
+            )
+        })
+    }
+}
+
+impl std::fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Recurrence::Every(n, unit) => write!(f, "every {}{}", n, unit.symbol()),
+            Recurrence::Weekday(day) => write!(f, "every {}", weekday_name(*day)),
+            Recurrence::MonthlyOn(1, day) => write!(f, "monthly on the {}", ordinal(*day)),
+            Recurrence::MonthlyOn(n, day) => write!(f, "every {}mo on the {}", n, ordinal(*day)),
+            Recurrence::AfterCompletion(n, unit) => {
+                write!(f, "{}{} after completion", n, unit.symbol())
+            }
+        }
+    }
+}
+
+impl Serialize for Recurrence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Recurrence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Recurrence, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Returns the date with the time of day taken from `time`. If the clocks are put forward
+/// over that time, the first time after the gap is used.
+/// None if it's out of the supported range.
+fn at_time_of(date: NaiveDate, time: DateFormat) -> Option<DateFormat> {
+    let start = date.and_time(time.time());
+    (0..=24 * 60).find_map(|minutes| {
+        let time = start.checked_add_signed(Duration::minutes(minutes))?;
+        Local.from_local_datetime(&time).earliest()
+    })
+}
+
+impl Recurrence {
+    /// Returns the due date of the next occurrence of a task that was due at `due`
+    /// and was done at `completed`. The next due date is always after `completed`.
+    /// Returns an error if it's out of the supported range of dates.
+    pub fn next_due(
+        &self,
+        due: Option<DateFormat>,
+        completed: DateFormat,
+    ) -> Result<DateFormat, String> {
+        let anchor = due.unwrap_or(completed);
+        let out_of_range = || format!("The next due date of '{}' is out of range!", self);
+        match *self {
+            Recurrence::Every(n, unit) => {
+                // counted from the due date, so months don't drift to shorter ones' last day,
+                // skipping the occurrences that were missed
+                let mut k = 1;
+                loop {
+                    let times = n.checked_mul(k).ok_or_else(out_of_range)?;
+                    let next = unit.add(anchor, times).ok_or_else(out_of_range)?;
+                    if next > completed {
+                        return Ok(next);
+                    }
+                    k += 1;
+                }
+            }
+            Recurrence::AfterCompletion(n, unit) => unit.add(completed, n).ok_or_else(out_of_range),
+            Recurrence::Weekday(weekday) => {
+                let mut date = anchor.max(completed).naive_local().date() + Duration::days(1);
+                while date.weekday() != weekday {
+                    date += Duration::days(1);
+                }
+                at_time_of(date, anchor).ok_or_else(out_of_range)
+            }
+            Recurrence::MonthlyOn(n, day) => {
+                let start = anchor.max(completed).naive_local().date();
+                // every n months from the month of the due date
+                let mut months = anchor.year() as i64 * 12 + anchor.month0() as i64;
+                loop {
+                    let year = i32::try_from(months.div_euclid(12)).map_err(|_| out_of_range())?;
+                    let month = months.rem_euclid(12) as u32 + 1;
+                    let date = day_in_month(year, month, day).ok_or_else(out_of_range)?;
+                    if date > start {
+                        return at_time_of(date, anchor).ok_or_else(out_of_range);
+                    }
+                    months += n as i64;
+                }
+            }
+        }
+    }
+
+    /// Returns the recurrence for the next occurrence of a task that was due at `due`.
+    /// Repeating every few months (or every year on Feb 29) after the 28th keeps the day
+    /// of the month, so it isn't lost after a shorter month.
+    pub fn keeping_day_of(self, due: DateFormat) -> Recurrence {
+        let months = match self {
+            Recurrence::Every(n, Unit::Month) if due.day() > 28 => Some(n),
+            Recurrence::Every(n, Unit::Year) if (due.month(), due.day()) == (2, 29) => {
+                n.checked_mul(12)
+            }
+            _ => None,
+        };
+        match months.filter(|months| *months <= MAX_INTERVAL) {
+            Some(months) => Recurrence::MonthlyOn(months, due.day()),
+            None => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> DateFormat {
+        let date = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&date).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        macro_rules! test_parse {
+            ($s:expr, $expected:expr) => {
+                assert_eq!($s.parse::<Recurrence>(), Ok($expected), "Parsing '{}'", $s);
+            };
+        }
+
+        test_parse!("every 2w", Recurrence::Every(2, Unit::Week));
+        test_parse!("Every 3 days", Recurrence::Every(3, Unit::Day));
+        test_parse!("every month", Recurrence::Every(1, Unit::Month));
+        test_parse!("daily", Recurrence::Every(1, Unit::Day));
+        test_parse!("every monday", Recurrence::Weekday(Weekday::Mon));
+        test_parse!("every fri", Recurrence::Weekday(Weekday::Fri));
+        test_parse!("monthly on the 1st", Recurrence::MonthlyOn(1, 1));
+        test_parse!("every month on the 22nd", Recurrence::MonthlyOn(1, 22));
+        test_parse!("every 2 months on the 31st", Recurrence::MonthlyOn(2, 31));
+        test_parse!(
+            "3d after completion",
+            Recurrence::AfterCompletion(3, Unit::Day)
+        );
+        test_parse!(
+            "every 2 weeks after completion",
+            Recurrence::AfterCompletion(2, Unit::Week)
+        );
+
+        assert!("every".parse::<Recurrence>().is_err());
+        assert!("every 0d".parse::<Recurrence>().is_err());
+        assert!("every 99999999y".parse::<Recurrence>().is_err());
+        assert!("every 4000000000w".parse::<Recurrence>().is_err());
+        assert!("monthly on the 32nd".parse::<Recurrence>().is_err());
+        assert!("every 2w on the 3rd".parse::<Recurrence>().is_err());
+        assert!("sometimes".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for s in [
+            "every 2w",
+            "every monday",
+            "monthly on the 1st",
+            "monthly on the 23rd",
+            "every 3mo on the 30th",
+            "3d after completion",
+            "every 1mo",
+        ] {
+            assert_eq!(s.parse::<Recurrence>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_next_due() {
+        let due = date("2026-11-02 09:00"); // a monday
+        let completed = date("2026-11-03 18:00");
+
+        assert_eq!(
+            Recurrence::Every(1, Unit::Week)
+                .next_due(Some(due), completed)
+                .unwrap(),
+            date("2026-11-09 09:00")
+        );
+        assert_eq!(
+            Recurrence::Every(1, Unit::Day)
+                .next_due(Some(due), completed)
+                .unwrap(),
+            date("2026-11-04 09:00"),
+            "Missed occurrences should be skipped"
+        );
+        assert_eq!(
+            Recurrence::Weekday(Weekday::Mon)
+                .next_due(Some(due), completed)
+                .unwrap(),
+            date("2026-11-09 09:00")
+        );
+        assert_eq!(
+            Recurrence::MonthlyOn(1, 1)
+                .next_due(Some(due), completed)
+                .unwrap(),
+            date("2026-12-01 09:00")
+        );
+        assert_eq!(
+            Recurrence::MonthlyOn(1, 31)
+                .next_due(Some(date("2026-11-30 09:00")), completed)
+                .unwrap(),
+            date("2026-12-31 09:00")
+        );
+        assert_eq!(
+            Recurrence::MonthlyOn(1, 31)
+                .next_due(Some(date("2026-10-31 09:00")), completed)
+                .unwrap(),
+            date("2026-11-30 09:00"),
+            "Shorter months should use their last day"
+        );
+        assert_eq!(
+            Recurrence::MonthlyOn(2, 31)
+                .next_due(Some(date("2026-10-31 09:00")), completed)
+                .unwrap(),
+            date("2026-12-31 09:00"),
+            "Every second month from the due date"
+        );
+        assert_eq!(
+            Recurrence::AfterCompletion(3, Unit::Day)
+                .next_due(Some(due), completed)
+                .unwrap(),
+            date("2026-11-06 18:00")
+        );
+
+        let far = Local.with_ymd_and_hms(262_000, 1, 1, 0, 0, 0).unwrap();
+        for recurrence in [
+            Recurrence::Every(MAX_INTERVAL, Unit::Year),
+            Recurrence::AfterCompletion(MAX_INTERVAL, Unit::Week),
+            Recurrence::Every(u32::MAX, Unit::Month),
+        ] {
+            assert!(
+                recurrence.next_due(Some(far), far).is_err(),
+                "{} should be out of range",
+                recurrence
+            );
+        }
+    }
+
+    #[test]
+    fn test_month_end() {
+        let every_month = Recurrence::Every(1, Unit::Month);
+        let due = date("2027-01-31 09:00");
+        assert_eq!(
+            every_month.next_due(Some(due), due).unwrap(),
+            date("2027-02-28 09:00")
+        );
+        assert_eq!(
+            every_month
+                .next_due(Some(due), date("2027-03-01 12:00"))
+                .unwrap(),
+            date("2027-03-31 09:00"),
+            "Missed occurrences should be counted from the due date"
+        );
+
+        let next = every_month.keeping_day_of(due);
+        assert_eq!(next, Recurrence::MonthlyOn(1, 31));
+        let feb = date("2027-02-28 09:00");
+        assert_eq!(
+            next.next_due(Some(feb), feb).unwrap(),
+            date("2027-03-31 09:00")
+        );
+
+        assert_eq!(
+            Recurrence::Every(1, Unit::Year).keeping_day_of(date("2028-02-29 09:00")),
+            Recurrence::MonthlyOn(12, 29)
+        );
+        assert_eq!(
+            every_month.keeping_day_of(date("2027-01-28 09:00")),
+            every_month
+        );
+        assert_eq!(
+            Recurrence::Every(1, Unit::Week).keeping_day_of(due),
+            Recurrence::Every(1, Unit::Week)
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! The versioned format of the save file and migrations from older versions.
+//!
+//! Version 1 was a bare array of tasks that used the string "None" for missing values
+//! and chrono's `to_string()` output for dates.
+//! Version 2 wraps the tasks in a document with a `version` field, uses `null` for missing
+//! values and RFC 3339 dates.
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::task::TaskJson;
+
+pub const CURRENT_VERSION: u64 = 2;
+
+/// A migration upgrades a document from version `n` to `n + 1`.
+type Migration = fn(Value) -> Result<Value, String>;
+
+/// `MIGRATIONS[n - 1]` upgrades version `n`.
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2];
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Document {
+    pub version: u64,
+    /// The id the next new task gets, so ids of removed tasks aren't reused.
+    #[serde(default)]
+    pub next_id: u32,
+    pub tasks: Vec<TaskJson>,
+}
+
+impl Document {
+    pub fn new(tasks: Vec<TaskJson>, next_id: u32) -> Document {
+        Document {
+            version: CURRENT_VERSION,
+            next_id,
+            tasks,
+        }
+    }
+}
+
+/// Parses a save file of any known version, upgrading it to the current one.
+pub fn parse(contents: &str) -> Result<Document, String> {
+    if contents.trim().is_empty() {
+        return Ok(Document::new(Vec::new(), 0));
+    }
+
+    let mut value: Value =
+        serde_json::from_str(contents).map_err(|e| format!("Could not parse file: {}", e))?;
+    let mut version = version_of(&value)?;
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "The file was saved by a newer version of stuff (format version {}, supported up to {})!",
+            version, CURRENT_VERSION
+        ));
+    }
+
+    while version < CURRENT_VERSION {
+        value = MIGRATIONS[version as usize - 1](value)
+            .map_err(|e| format!("Could not upgrade file from version {}: {}", version, e))?;
+        version += 1;
+    }
+
+    serde_json::from_value(value).map_err(|e| format!("Could not parse file: {}", e))
+}
+
+fn version_of(value: &Value) -> Result<u64, String> {
+    match value {
+        Value::Array(_) => Ok(1),
+        Value::Object(doc) => match doc.get("version").and_then(Value::as_u64) {
+            Some(version) if version > 0 => Ok(version),
+            _ => Err("Could not parse file: missing or invalid version".into()),
+        },
+        _ => Err("Could not parse file: unknown format".into()),
+    }
+}
+
+fn migrate_v1_to_v2(value: Value) -> Result<Value, String> {
+    fn migrate_task(task: Value) -> Result<Value, String> {
+        let mut task = match task {
+            Value::Object(task) => task,
+            _ => return Err("a task is not an object".into()),
+        };
+
+        for field in ["description", "due_date", "date_completed"] {
+            if matches!(task.get(field), Some(Value::String(s)) if s == "None") {
+                task.insert(field.into(), Value::Null);
+            }
+        }
+        for field in ["due_date", "date_created", "date_completed"] {
+            if let Some(Value::String(date)) = task.get(field) {
+                let date = date
+                    .parse::<DateTime<FixedOffset>>()
+                    .map_err(|e| format!("invalid date '{}': {}", date, e))?;
+                task.insert(field.into(), Value::String(date.to_rfc3339()));
+            }
+        }
+        task.entry("date_completed").or_insert(Value::Null);
+
+        if let Some(Value::Array(sub_tasks)) = task.remove("sub_tasks") {
+            let sub_tasks = sub_tasks
+                .into_iter()
+                .map(migrate_task)
+                .collect::<Result<_, _>>()?;
+            task.insert("sub_tasks".into(), Value::Array(sub_tasks));
+        }
+        Ok(Value::Object(task))
+    }
+
+    let tasks = match value {
+        Value::Array(tasks) => tasks,
+        _ => return Err("expected an array of tasks".into()),
+    };
+    let tasks = tasks
+        .into_iter()
+        .map(migrate_task)
+        .collect::<Result<_, _>>()?;
+
+    let mut doc = Map::new();
+    doc.insert("version".into(), Value::from(2));
+    doc.insert("tasks".into(), Value::Array(tasks));
+    Ok(Value::Object(doc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_v1() {
+        let v1 = r#"[{"title":"A","description":"None","importance":2,"due_date":"2026-10-18 20:45:32.126834698 +02:00","date_created":"2026-10-17 20:45:32.126885485 +00:00","sub_tasks":[{"id":3,"title":"B","description":"desc","importance":0,"due_date":"None","date_created":"2026-10-17 20:45:32.127359719 +00:00","date_completed":"2026-10-17 21:00:00 +00:00","sub_tasks":[]}]}]"#;
+        let doc = parse(v1).unwrap();
+        assert_eq!(doc.version, CURRENT_VERSION);
+
+        let json = serde_json::to_value(&doc.tasks).unwrap();
+        assert_eq!(json[0]["description"], Value::Null);
+        assert_eq!(json[0]["date_completed"], Value::Null);
+        let due = json[0]["due_date"].as_str().unwrap();
+        assert_eq!(
+            DateTime::parse_from_rfc3339(due).unwrap(),
+            DateTime::parse_from_rfc3339("2026-10-18T20:45:32.126834698+02:00").unwrap()
+        );
+        assert_eq!(json[0]["sub_tasks"][0]["id"], 3);
+        assert_eq!(json[0]["sub_tasks"][0]["description"], "desc");
+        assert_eq!(json[0]["sub_tasks"][0]["due_date"], Value::Null);
+        assert!(json[0]["sub_tasks"][0]["date_completed"].is_string());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("").unwrap().tasks.is_empty());
+        assert!(parse("[]").unwrap().tasks.is_empty());
+        assert!(parse(r#"{"version":2,"tasks":[]}"#).is_ok());
+        assert!(
+            parse(r#"{"version":99,"tasks":[]}"#).is_err(),
+            "Files from newer versions shouldn't be loaded"
+        );
+        assert!(parse(r#"[{"title":"A"}]"#).is_err());
+        assert!(parse("not json").is_err());
+    }
+}
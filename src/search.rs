@@ -0,0 +1,206 @@
+//! Fuzzy search through the titles and descriptions of tasks.
+
+use crate::task::{Task, TaskList};
+
+/// A match of a pattern in a text.
+#[derive(Debug, PartialEq)]
+pub struct Match {
+    pub score: i64,
+    /// Positions (in chars) of the matched characters.
+    pub positions: Vec<usize>,
+}
+
+/// A task that matches a search, with the matched characters of its title and description.
+pub struct Hit<'a> {
+    pub path: Vec<usize>,
+    pub task: &'a Task,
+    pub score: i64,
+    pub title_positions: Vec<usize>,
+    pub description_positions: Vec<usize>,
+}
+
+fn is_word_start(text: &[char], i: usize) -> bool {
+    i == 0 || !text[i - 1].is_alphanumeric()
+}
+
+fn lowercase_chars(s: &str) -> Vec<char> {
+    // one char per char, so that the positions match the original text
+    s.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// Matches the pattern against the text, ignoring case.
+/// Exact substrings score the highest, otherwise the characters of the pattern
+/// have to appear in the text in order. Matches at word starts and consecutive characters score higher.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    let pattern = lowercase_chars(pattern);
+    let text = lowercase_chars(text);
+    if pattern.is_empty() || pattern.len() > text.len() {
+        return None;
+    }
+
+    let substring = (0..=text.len() - pattern.len())
+        .filter(|&i| text[i..i + pattern.len()] == pattern[..])
+        .max_by_key(|&i| (is_word_start(&text, i), std::cmp::Reverse(i)));
+    if let Some(i) = substring {
+        let mut score = 100 + pattern.len() as i64 * 10 - (i as i64).min(20);
+        if is_word_start(&text, i) {
+            score += 20;
+        }
+        return Some(Match {
+            score,
+            positions: (i..i + pattern.len()).collect(),
+        });
+    }
+
+    let mut positions: Vec<usize> = Vec::with_capacity(pattern.len());
+    let mut score = 0;
+    let mut chars = pattern.iter().peekable();
+    for (i, c) in text.iter().enumerate() {
+        let Some(&&p) = chars.peek() else {
+            break;
+        };
+        if *c != p {
+            continue;
+        }
+        chars.next();
+        score += 5;
+        if is_word_start(&text, i) {
+            score += 8;
+        }
+        match positions.last() {
+            Some(&last) if last + 1 == i => score += 5,
+            // gaps between the matched characters
+            Some(&last) => score -= (i - last - 1).min(10) as i64,
+            None => {}
+        }
+        positions.push(i);
+    }
+
+    if chars.peek().is_some() || score <= 0 {
+        return None;
+    }
+    Some(Match { score, positions })
+}
+
+/// Returns the text with the characters at the positions underlined.
+pub fn highlight(text: &str, positions: &[usize]) -> String {
+    if positions.is_empty() {
+        return text.to_string();
+    }
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if positions.contains(&i) {
+                format!("\x1b[4m{}\x1b[24m", c)
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Searches all tasks and sub-tasks. Every word of the query has to match the title or
+/// the description. The best hits come first, done tasks after the ones that aren't done.
+pub fn search<'a>(task_list: &'a TaskList, query: &str) -> Vec<Hit<'a>> {
+    let words: Vec<&str> = query.split_whitespace().collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+
+    let mut hits: Vec<Hit> = task_list
+        .flatten()
+        .into_iter()
+        .filter_map(|(path, task)| {
+            let mut hit = Hit {
+                path,
+                task,
+                score: 0,
+                title_positions: Vec::new(),
+                description_positions: Vec::new(),
+            };
+            for word in words.iter() {
+                let title = fuzzy_match(word, &task.title);
+                let description = task
+                    .description
+                    .as_deref()
+                    .and_then(|desc| fuzzy_match(word, desc));
+
+                // matches in the title are worth more
+                match (title, description) {
+                    (Some(title), description)
+                        if description
+                            .as_ref()
+                            .is_none_or(|d| title.score * 2 >= d.score) =>
+                    {
+                        hit.score += title.score * 2;
+                        hit.title_positions.extend(title.positions);
+                    }
+                    (_, Some(description)) => {
+                        hit.score += description.score;
+                        hit.description_positions.extend(description.positions);
+                    }
+                    _ => return None,
+                }
+            }
+            Some(hit)
+        })
+        .collect();
+
+    hits.sort_by_key(|hit| (hit.task.is_done(), std::cmp::Reverse(hit.score)));
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert_eq!(
+            fuzzy_match("milk", "Buy Milk").unwrap().positions,
+            vec![4, 5, 6, 7]
+        );
+        assert_eq!(
+            fuzzy_match("bml", "Buy milk").unwrap().positions,
+            vec![0, 4, 6]
+        );
+        assert!(fuzzy_match("klim", "Buy milk").is_none());
+        assert!(fuzzy_match("", "Buy milk").is_none());
+
+        let exact = fuzzy_match("rep", "Write report").unwrap();
+        let fuzzy = fuzzy_match("rep", "Refill the paper").unwrap();
+        assert!(exact.score > fuzzy.score);
+        assert!(
+            fuzzy_match("port", "Port the app").unwrap().score
+                > fuzzy_match("port", "Write report").unwrap().score,
+            "Matches at the start of a word should score higher"
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        task_list.add_task(Task::new("Write report".into(), None, 0, None));
+        task_list.add_task(Task::new(
+            "Groceries".into(),
+            Some("milk, eggs".into()),
+            0,
+            None,
+        ));
+        task_list
+            .insert(&[2], Task::new("Buy milk".into(), None, 0, None))
+            .unwrap();
+
+        let hits = search(&task_list, "milk");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].path, vec![2, 1], "Title matches should rank first");
+        assert_eq!(hits[1].description_positions, vec![0, 1, 2, 3]);
+
+        assert_eq!(search(&task_list, "write rep").len(), 1);
+        assert!(search(&task_list, "write eggs").is_empty());
+        assert!(search(&task_list, "").is_empty());
+    }
+}
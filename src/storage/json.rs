@@ -0,0 +1,77 @@
+//! Storage in a JSON file with rotating backups.
+
+use std::path::{Path, PathBuf};
+
+use super::Storage;
+use crate::backup::{self, Backup};
+use crate::schema::{self, Document};
+
+pub struct JsonFileStorage {
+    path: PathBuf,
+    backed_up: bool,
+    load_failed: bool,
+}
+
+impl JsonFileStorage {
+    pub fn new(path: PathBuf) -> JsonFileStorage {
+        JsonFileStorage {
+            path,
+            backed_up: false,
+            load_failed: false,
+        }
+    }
+
+    fn read(path: &Path) -> Result<Document, String> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| format!("Could not read file: {}", e))?;
+        schema::parse(&contents)
+    }
+}
+
+impl Storage for JsonFileStorage {
+    fn load(&mut self) -> Result<Document, String> {
+        if !self.path.exists() {
+            return Ok(Document::new(Vec::new(), 0));
+        }
+
+        let document = Self::read(&self.path);
+        // don't overwrite a file that we couldn't read
+        self.load_failed = document.is_err();
+        document
+    }
+
+    fn save(&mut self, document: &Document) -> Result<(), String> {
+        if self.load_failed {
+            return Err("Not saving because the save file could not be loaded".into());
+        }
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create directory: {}", e))?;
+        }
+
+        // back up the previous version once per run
+        if !self.backed_up {
+            if let Err(e) = backup::create_backup(&self.path) {
+                eprintln!("Could not create backup: {}", e);
+            }
+            self.backed_up = true;
+        }
+
+        let json = serde_json::to_string(document).unwrap();
+        backup::write_atomic(&self.path, json.as_bytes())
+            .map_err(|e| format!("Could not write to file: {}", e))
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn backups(&self) -> Vec<Backup> {
+        backup::list_backups(&self.path)
+    }
+
+    fn load_backup(&self, backup: &Backup) -> Result<Document, String> {
+        Self::read(&backup.path)
+    }
+}
@@ -0,0 +1,26 @@
+//! Storage that keeps the tasks in memory, used in tests.
+
+use std::path::Path;
+
+use super::Storage;
+use crate::schema::{self, Document};
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    contents: String,
+}
+
+impl Storage for MemoryStorage {
+    fn load(&mut self) -> Result<Document, String> {
+        schema::parse(&self.contents)
+    }
+
+    fn save(&mut self, document: &Document) -> Result<(), String> {
+        self.contents = serde_json::to_string(document).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+}
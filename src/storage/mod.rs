@@ -0,0 +1,78 @@
+//! Storage backends the tasks can be saved in.
+
+mod json;
+#[cfg(test)]
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use std::path::{Path, PathBuf};
+
+use crate::backup::Backup;
+use crate::journal::Journal;
+use crate::schema::Document;
+
+pub use json::JsonFileStorage;
+#[cfg(test)]
+pub use memory::MemoryStorage;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+pub trait Storage {
+    /// Loads all saved tasks. Returns an empty document if nothing was saved yet.
+    fn load(&mut self) -> Result<Document, String>;
+
+    /// Saves all tasks. Backends may only write the tasks that changed since the last save.
+    fn save(&mut self, document: &Document) -> Result<(), String>;
+
+    /// The file the tasks are stored in. None if the tasks aren't stored in a file.
+    fn path(&self) -> Option<&Path>;
+
+    /// Loads the undo journal. By default it's kept in a file next to the save file.
+    fn load_journal(&mut self) -> Journal {
+        match self.path() {
+            Some(path) => Journal::load(&Journal::path_for(path)),
+            None => Journal::default(),
+        }
+    }
+
+    /// Saves the undo journal.
+    fn save_journal(&mut self, journal: &Journal) -> Result<(), String> {
+        match self.path() {
+            Some(path) => journal.save(&Journal::path_for(path)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the backups of the saved tasks, newest first.
+    fn backups(&self) -> Vec<Backup> {
+        Vec::new()
+    }
+
+    /// Loads the tasks from a backup returned by `backups`.
+    fn load_backup(&self, _backup: &Backup) -> Result<Document, String> {
+        Err("This storage doesn't support backups!".into())
+    }
+}
+
+/// Opens the storage for the file, choosing the backend by its extension.
+pub fn open(path: PathBuf) -> Result<Box<dyn Storage>, String> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match extension {
+        "db" | "sqlite" | "sqlite3" => open_sqlite(path),
+        _ => Ok(Box::new(JsonFileStorage::new(path))),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(path: PathBuf) -> Result<Box<dyn Storage>, String> {
+    Ok(Box::new(SqliteStorage::open(path)?))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(path: PathBuf) -> Result<Box<dyn Storage>, String> {
+    Err(format!(
+        "Can't open {}: stuff was built without SQLite support (enable the `sqlite` feature)",
+        path.display()
+    ))
+}
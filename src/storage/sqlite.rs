@@ -0,0 +1,402 @@
+//! Storage in a SQLite database with one row per task.
+//! Saving only writes the rows of the tasks that changed since the last load or save.
+//! The undo journal is kept in the database too, with one row per entry.
+//! Journals of older versions, which stored all tasks in every entry, are dropped.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::Storage;
+use crate::journal::{Entry, Journal};
+use crate::schema::{Document, CURRENT_VERSION};
+use crate::task::TaskJson;
+
+/// A task as it is stored in a row.
+#[derive(PartialEq, Clone)]
+struct Row {
+    parent: Option<u32>,
+    position: usize,
+    /// The task without its sub-tasks, as json.
+    data: String,
+}
+
+pub struct SqliteStorage {
+    path: PathBuf,
+    connection: Connection,
+    /// The rows as they are in the database.
+    saved: HashMap<u32, Row>,
+    /// The journal entries that are in the database.
+    saved_entries: HashSet<u64>,
+    load_failed: bool,
+}
+
+impl SqliteStorage {
+    pub fn open(path: PathBuf) -> Result<SqliteStorage, String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create directory: {}", e))?;
+        }
+        let connection = Connection::open(&path).map_err(|e| e.to_string())?;
+        if connection.prepare("SELECT tasks FROM journal").is_ok() {
+            connection
+                .execute_batch("DROP TABLE journal")
+                .map_err(|e| e.to_string())?;
+        }
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+                 CREATE TABLE IF NOT EXISTS tasks (
+                     id INTEGER PRIMARY KEY,
+                     parent INTEGER,
+                     position INTEGER NOT NULL,
+                     data TEXT NOT NULL
+                 );
+                 CREATE TABLE IF NOT EXISTS journal (
+                     seq INTEGER PRIMARY KEY,
+                     redo INTEGER NOT NULL,
+                     command TEXT NOT NULL,
+                     date TEXT NOT NULL,
+                     changes TEXT NOT NULL
+                 );",
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(SqliteStorage {
+            path,
+            connection,
+            saved: HashMap::new(),
+            saved_entries: HashSet::new(),
+            load_failed: false,
+        })
+    }
+
+    fn get_meta(&self, key: &str) -> Result<Option<String>, String> {
+        self.connection
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Flattens the task tree into rows.
+    fn to_rows(tasks: &[TaskJson], parent: Option<u32>, rows: &mut HashMap<u32, Row>) {
+        for (position, task) in tasks.iter().enumerate() {
+            let mut data = task.clone();
+            data.sub_tasks = Vec::new();
+            rows.insert(
+                task.id,
+                Row {
+                    parent,
+                    position,
+                    data: serde_json::to_string(&data).unwrap(),
+                },
+            );
+            Self::to_rows(&task.sub_tasks, Some(task.id), rows);
+        }
+    }
+
+    /// Builds the task tree back from the rows.
+    fn from_rows(rows: &HashMap<u32, Row>) -> Result<Vec<TaskJson>, String> {
+        let mut children: HashMap<Option<u32>, Vec<(usize, u32)>> = HashMap::new();
+        for (&id, row) in rows {
+            children
+                .entry(row.parent)
+                .or_default()
+                .push((row.position, id));
+        }
+
+        fn build(
+            parent: Option<u32>,
+            rows: &HashMap<u32, Row>,
+            children: &mut HashMap<Option<u32>, Vec<(usize, u32)>>,
+        ) -> Result<Vec<TaskJson>, String> {
+            let mut ids = children.remove(&parent).unwrap_or_default();
+            ids.sort();
+            ids.into_iter()
+                .map(|(_, id)| {
+                    let mut task: TaskJson = serde_json::from_str(&rows[&id].data)
+                        .map_err(|e| format!("Could not parse task #{}: {}", id, e))?;
+                    task.sub_tasks = build(Some(id), rows, children)?;
+                    Ok(task)
+                })
+                .collect()
+        }
+
+        build(None, rows, &mut children)
+    }
+
+    fn read(&mut self) -> Result<Document, String> {
+        if let Some(version) = self.get_meta("version")? {
+            if version.parse::<u64>().unwrap_or(u64::MAX) > CURRENT_VERSION {
+                return Err(format!(
+                    "The database was saved by a newer version of stuff (format version {})!",
+                    version
+                ));
+            }
+        }
+        let next_id = self
+            .get_meta("next_id")?
+            .and_then(|id| id.parse().ok())
+            .unwrap_or(0);
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, parent, position, data FROM tasks")
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    Row {
+                        parent: row.get(1)?,
+                        position: row.get::<_, i64>(2)? as usize,
+                        data: row.get(3)?,
+                    },
+                ))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let tasks = Self::from_rows(&rows)?;
+        self.saved = rows;
+        Ok(Document::new(tasks, next_id))
+    }
+
+    fn read_journal(&self) -> Result<Vec<(bool, Entry)>, String> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT seq, redo, command, date, changes FROM journal ORDER BY seq")
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u64,
+                    row.get::<_, bool>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        rows.into_iter()
+            .map(|(seq, redo, command, date, changes)| {
+                let changes = serde_json::from_str(&changes)
+                    .map_err(|e| format!("Could not parse undo journal entry {}: {}", seq, e))?;
+                Ok((
+                    redo,
+                    Entry {
+                        seq,
+                        command,
+                        date,
+                        changes,
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&mut self) -> Result<Document, String> {
+        let document = self.read();
+        // don't overwrite a database that we couldn't read
+        self.load_failed = document.is_err();
+        document
+    }
+
+    fn save(&mut self, document: &Document) -> Result<(), String> {
+        if self.load_failed {
+            return Err("Not saving because the database could not be loaded".into());
+        }
+
+        let mut rows = HashMap::new();
+        Self::to_rows(&document.tasks, None, &mut rows);
+
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut upsert = transaction
+                .prepare_cached(
+                    "INSERT INTO tasks (id, parent, position, data) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT(id) DO UPDATE SET
+                         parent = excluded.parent, position = excluded.position, data = excluded.data",
+                )
+                .map_err(|e| e.to_string())?;
+            for (id, row) in rows.iter() {
+                if self.saved.get(id) != Some(row) {
+                    upsert
+                        .execute(params![id, row.parent, row.position as i64, row.data])
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+
+            let mut delete = transaction
+                .prepare_cached("DELETE FROM tasks WHERE id = ?1")
+                .map_err(|e| e.to_string())?;
+            for id in self.saved.keys() {
+                if !rows.contains_key(id) {
+                    delete.execute([id]).map_err(|e| e.to_string())?;
+                }
+            }
+
+            let mut set_meta = transaction
+                .prepare_cached("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)")
+                .map_err(|e| e.to_string())?;
+            set_meta
+                .execute(["version", &CURRENT_VERSION.to_string()])
+                .map_err(|e| e.to_string())?;
+            set_meta
+                .execute(["next_id", &document.next_id.to_string()])
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())?;
+
+        self.saved = rows;
+        Ok(())
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn load_journal(&mut self) -> Journal {
+        match self.read_journal() {
+            Ok(entries) => {
+                self.saved_entries = entries.iter().map(|(_, entry)| entry.seq).collect();
+                Journal::from_entries(entries)
+            }
+            Err(e) => {
+                eprintln!("Could not load undo journal, starting a new one: {}", e);
+                Journal::default()
+            }
+        }
+    }
+
+    /// Only writes the new entries and deletes the ones that were dropped.
+    fn save_journal(&mut self, journal: &Journal) -> Result<(), String> {
+        if self.load_failed {
+            return Err("Not saving because the database could not be loaded".into());
+        }
+
+        let entries: HashSet<u64> = journal.entries().map(|(_, entry)| entry.seq).collect();
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut insert = transaction
+                .prepare_cached(
+                    "INSERT OR REPLACE INTO journal (seq, redo, command, date, changes)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )
+                .map_err(|e| e.to_string())?;
+            for (redo, entry) in journal.entries() {
+                if !self.saved_entries.contains(&entry.seq) {
+                    let changes = serde_json::to_string(&entry.changes).unwrap();
+                    insert
+                        .execute(params![
+                            entry.seq as i64,
+                            redo,
+                            entry.command,
+                            entry.date,
+                            changes
+                        ])
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+
+            let mut delete = transaction
+                .prepare_cached("DELETE FROM journal WHERE seq = ?1")
+                .map_err(|e| e.to_string())?;
+            for seq in self.saved_entries.difference(&entries) {
+                delete.execute([*seq as i64]).map_err(|e| e.to_string())?;
+            }
+        }
+        transaction.commit().map_err(|e| e.to_string())?;
+
+        self.saved_entries = entries;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{Task, TaskList};
+
+    #[test]
+    fn test_sqlite_storage() {
+        let path =
+            std::env::temp_dir().join(format!("stuff-sqlite-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut task_list = TaskList::new(Box::new(SqliteStorage::open(path.clone()).unwrap()));
+        task_list.add_task(Task::new("a".into(), None, 0, None));
+        task_list.add_task(Task::new("b".into(), None, 0, None));
+        task_list
+            .insert(&[2], Task::new("c".into(), None, 0, None))
+            .unwrap();
+        task_list.save().unwrap();
+        task_list.remove(&[1]).unwrap();
+        task_list.save().unwrap();
+
+        let mut task_list = TaskList::new(Box::new(SqliteStorage::open(path.clone()).unwrap()));
+        task_list.load();
+        assert_eq!(task_list.tasks.len(), 1);
+        assert_eq!(task_list.tasks[0].title, "b");
+        assert_eq!(task_list.tasks[0].sub_tasks[0].title, "c");
+        assert_eq!(task_list.add_task(Task::new("d".into(), None, 0, None)), 4);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_journal_and_load_failed() {
+        let path = std::env::temp_dir().join(format!(
+            "stuff-sqlite-journal-test-{}.db",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let mut task_list = TaskList::new(Box::new(SqliteStorage::open(path.clone()).unwrap()));
+        task_list.load();
+        for title in ["a", "b"] {
+            let before = task_list.snapshot();
+            task_list.add_task(Task::new(title.into(), None, 0, None));
+            let after = task_list.snapshot();
+            task_list
+                .journal
+                .record(format!("add {}", title), &before, &after);
+            task_list.save().unwrap();
+        }
+        let entry = task_list.journal.undo().unwrap();
+        task_list.restore(entry.revert(&task_list.snapshot()));
+        task_list.save().unwrap();
+
+        let mut task_list = TaskList::new(Box::new(SqliteStorage::open(path.clone()).unwrap()));
+        task_list.load();
+        assert_eq!(task_list.tasks.len(), 1);
+        assert!(task_list.journal.redo().is_some());
+        assert_eq!(task_list.journal.undo().unwrap().command, "add b");
+
+        // a database saved by a newer version must not be overwritten
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute("UPDATE meta SET value = '99' WHERE key = 'version'", [])
+            .unwrap();
+        let mut task_list = TaskList::new(Box::new(SqliteStorage::open(path.clone()).unwrap()));
+        task_list.load();
+        task_list.add_task(Task::new("c".into(), None, 0, None));
+        assert!(task_list.save().is_err());
+        let version: String = connection
+            .query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(version, "99");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
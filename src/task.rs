@@ -1,62 +1,142 @@
 //! A module for storing and displaying task data.
 
+use crate::config::{Config, UrgencyWeights};
 use crate::date::DateFormat;
+use crate::formats::MAX_DEPTH;
+use crate::journal::Journal;
+use crate::recurrence::Recurrence;
+use crate::schema::Document;
+use crate::storage::Storage;
 use chrono::{Duration, Local};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::{io::{Read, Write}, path::PathBuf};
+use std::collections::{BTreeSet, HashSet};
 
 pub const IMPORTANCE_MAX: u8 = 3;
-pub const SAVE_FILE_NAME: &str = "saved_stuff.json";
 
-#[derive(Serialize, Deserialize, Debug)]
-struct TaskJson {
+/// The form a task is saved in. Missing values are `null` and dates are in RFC 3339 format.
+/// Incompatible changes require a new version and migration in the `schema` module.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TaskJson {
+    #[serde(default)]
+    pub(crate) id: u32,
     title: String,
-    description: String,
+    description: Option<String>,
     importance: u8,
-    due_date: String,
-    date_created: String,
-    sub_tasks: Vec<TaskJson>,
+    due_date: Option<DateFormat>,
+    date_created: DateFormat,
+    date_completed: Option<DateFormat>,
+    #[serde(default)]
+    tags: BTreeSet<String>,
+    #[serde(default)]
+    recurrence: Option<Recurrence>,
+    #[serde(default)]
+    pub(crate) sub_tasks: Vec<TaskJson>,
 }
 
-impl TaskJson {
-    fn new(task: &Task) -> Self {
-        let description = if let Some(desc) = task.description.clone() {
-            desc.to_string()
-        } else {
-            "None".to_string()
-        };
+/// Parses a task path like `3.2.1` (the first sub-task of the second sub-task of the third task).
+pub fn parse_path(path: &str) -> Result<Vec<usize>, String> {
+    path.split('.')
+        .map(|ind| match ind.parse::<usize>() {
+            Ok(ind) if ind > 0 => Ok(ind),
+            _ => Err(format!(
+                "Invalid task path '{}'! (use positive numbers separated by dots, e.g. 3.2.1)",
+                path
+            )),
+        })
+        .collect()
+}
 
-        let due_date = if let Some(due_date) = task.due_date {
-            due_date.to_string()
-        } else {
-            "None".to_string()
-        };
-        let date_created = task.date_created.to_string();
+/// Parses a tag written as `+tag` (or `-tag` if prefix is '-'). Tags start with a letter,
+/// so that e.g. `+1` isn't one.
+pub fn parse_tag(word: &str, prefix: char) -> Option<String> {
+    let tag = word.strip_prefix(prefix)?;
+    if tag.starts_with(|c: char| c.is_alphabetic()) {
+        Some(tag.to_lowercase())
+    } else {
+        None
+    }
+}
 
-        let mut sub_tasks = Vec::new();
-        for t in task.sub_tasks.iter() {
-            sub_tasks.push(TaskJson::new(&t));
-        }
+/// Returns true if the argument looks like a task id or path.
+pub fn is_selector(arg: &str) -> bool {
+    parse_id(arg).is_some() || parse_path(arg).is_ok()
+}
+
+/// Parses a task id written as `#12` or `id:12`. Returns None if the argument isn't an id.
+fn parse_id(arg: &str) -> Option<Result<u32, String>> {
+    let id = arg.strip_prefix('#').or_else(|| arg.strip_prefix("id:"))?;
+    Some(match id.parse::<u32>() {
+        Ok(id) if id > 0 => Ok(id),
+        _ => Err(format!("Invalid task id '{}'!", arg)),
+    })
+}
 
+/// Returns the color code used for the titles of tasks with the importance.
+pub fn importance_color(importance: u8) -> &'static str {
+    match importance {
+        3 => "\x1b[1;37;31m",
+        2 => "\x1b[1;37;33m",
+        1 => "\x1b[1;37;36m",
+        _ => "\x1b[1m",
+    }
+}
+
+pub fn format_path(path: &[usize]) -> String {
+    path.iter()
+        .map(|ind| ind.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Returns the number of tasks, e.g. "1 task" or "3 tasks".
+pub fn format_count(count: usize) -> String {
+    format!("{} task{}", count, if count == 1 { "" } else { "s" })
+}
+
+impl TaskJson {
+    fn new(task: &Task) -> Self {
         TaskJson {
+            id: task.id,
             title: task.title.clone(),
-            description: description,
+            description: task.description.clone(),
             importance: task.importance,
-            due_date,
-            date_created,
-            sub_tasks,
+            due_date: task.due_date,
+            date_created: task.date_created,
+            date_completed: task.date_completed,
+            tags: task.tags.clone(),
+            recurrence: task.recurrence,
+            sub_tasks: task.sub_tasks.iter().map(TaskJson::new).collect(),
         }
     }
+
+    /// Converts the json back into a task, including all of its sub-tasks.
+    fn into_task(self) -> Task {
+        let mut task = Task::new(self.title, self.description, self.importance, self.due_date);
+        task.id = self.id;
+        task.date_created = self.date_created;
+        task.date_completed = self.date_completed;
+        task.tags = self.tags;
+        task.recurrence = self.recurrence;
+        task.sub_tasks = self.sub_tasks.into_iter().map(|t| t.into_task()).collect();
+        task
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Task {
+    /// A unique id that doesn't change when the tasks are reordered. 0 means it wasn't assigned yet.
+    pub id: u32,
     pub title: String,
     pub description: Option<String>,
     pub importance: u8,
     pub due_date: Option<DateFormat>,
     pub date_created: DateFormat,
+    pub date_completed: Option<DateFormat>,
+    /// Tags are lowercase and written as `+tag`.
+    pub tags: BTreeSet<String>,
+    /// Recurring tasks are created again with the next due date when they are done.
+    pub recurrence: Option<Recurrence>,
     pub sub_tasks: Vec<Task>,
 }
 
@@ -68,121 +148,480 @@ impl Task {
         due_date: Option<DateFormat>,
     ) -> Task {
         Task {
+            id: 0,
             title,
             description,
             importance,
             due_date,
             date_created: Local::now(),
+            date_completed: None,
+            tags: BTreeSet::new(),
+            recurrence: None,
             sub_tasks: Vec::new(),
         }
     }
 
-    pub fn print_header(&self) {
-        match self.importance {
-            3 => print!("\x1b[1;37;31m{}\x1b[0m", self.title),
-            2 => print!("\x1b[1;37;33m{}\x1b[0m", self.title),
-            1 => print!("\x1b[1;37;36m{}\x1b[0m", self.title),
-            _ => print!("\x1b[1m{}\x1b[0m", self.title),
+    pub fn is_done(&self) -> bool {
+        self.date_completed.is_some()
+    }
+
+    /// Takes the `+tag` words (and `-tag` words if `allow_remove`) out of the input
+    /// and adds (or removes) the tags. Returns the rest of the input as it was written, trimmed.
+    pub fn apply_tags(&mut self, input: &str, allow_remove: bool) -> String {
+        let mut rest = String::new();
+        // the end of the last word, so the spaces before a word can be kept with it
+        let mut end = 0;
+        while let Some(start) = input[end..].find(|c: char| !c.is_whitespace()) {
+            let start = end + start;
+            let word_end = input[start..]
+                .find(char::is_whitespace)
+                .map_or(input.len(), |i| start + i);
+            let word = &input[start..word_end];
+            if let Some(tag) = parse_tag(word, '+') {
+                self.tags.insert(tag);
+            } else if let Some(tag) = parse_tag(word, '-').filter(|_| allow_remove) {
+                self.tags.remove(&tag);
+            } else if rest.is_empty() {
+                rest += word;
+            } else {
+                rest += &input[end..word_end];
+            }
+            end = word_end;
+        }
+        rest
+    }
+
+    /// Returns the next occurrence of a recurring task that is done:
+    /// a not done copy with the next due date. Due dates of the sub-tasks are moved along.
+    /// Returns an error if a due date would be out of range.
+    pub fn next_occurrence(&self) -> Result<Option<Task>, String> {
+        let (Some(recurrence), Some(completed)) = (self.recurrence, self.date_completed) else {
+            return Ok(None);
+        };
+        let due = recurrence.next_due(self.due_date, completed)?;
+
+        fn reset(task: &mut Task, shift: Option<Duration>) -> Result<(), String> {
+            task.id = 0;
+            task.date_created = Local::now();
+            task.date_completed = None;
+            if let (Some(due_date), Some(shift)) = (task.due_date, shift) {
+                let due_date = due_date.checked_add_signed(shift).ok_or_else(|| {
+                    format!(
+                        "The next due date of sub-task '{}' is out of range!",
+                        task.title
+                    )
+                })?;
+                task.due_date = Some(due_date);
+            }
+            for sub_task in task.sub_tasks.iter_mut() {
+                reset(sub_task, shift)?;
+            }
+            Ok(())
+        }
+
+        let mut next = self.clone();
+        reset(&mut next, self.due_date.map(|old| due - old))?;
+        next.due_date = Some(due);
+        if let Some(old) = self.due_date {
+            next.recurrence = Some(recurrence.keeping_day_of(old));
         }
+        Ok(Some(next))
+    }
+
+    /// Returns a score of how urgently the task should be done, see `UrgencyWeights`.
+    /// Done tasks aren't urgent.
+    pub fn urgency(&self, weights: &UrgencyWeights) -> f64 {
+        if self.is_done() {
+            return 0.0;
+        }
+        let now = Local::now();
+        let mut urgency = weights.importance * self.importance as f64;
 
         if let Some(due_date) = self.due_date {
-            let time_left = crate::date::get_time_left(due_date);
+            // 1 when overdue by a week, down to 0.2 when due in two weeks or later
+            let days_left = (due_date - now).num_minutes() as f64 / (60.0 * 24.0);
+            let factor = ((14.0 - days_left) / 21.0 * 0.8 + 0.2).clamp(0.2, 1.0);
+            urgency += weights.due * factor;
+        }
+
+        let age = (now - self.date_created).num_days() as f64 / 365.0;
+        urgency += weights.age * age.clamp(0.0, 1.0);
 
-            print!("\t");
-            if due_date - Local::now() < chrono::Duration::days(1) {
-                print!("\x1b[1;37;31m");
+        let pending = self.sub_tasks.iter().filter(|t| !t.is_done()).count();
+        urgency += weights.sub_tasks * pending.min(3) as f64 / 3.0;
+
+        urgency
+            + self
+                .tags
+                .iter()
+                .filter_map(|tag| weights.tags.get(tag))
+                .sum::<f64>()
+    }
+
+    fn tags_string(&self) -> String {
+        self.tags
+            .iter()
+            .map(|tag| format!(" \x1b[2;37;35m+{}\x1b[0m", tag))
+            .collect()
+    }
+
+    /// Returns the colored title line of the task,
+    /// with the characters of the title at `highlight` underlined.
+    pub fn header(&self, highlight: &[usize]) -> String {
+        let title = crate::search::highlight(&self.title, highlight);
+        if let Some(date_completed) = self.date_completed {
+            return format!(
+                "\x1b[2;9m{}\x1b[0m \x1b[2;37;37m#{}\x1b[0m{}\t\x1b[2;37;37m[done {}]\x1b[0m",
+                title,
+                self.id,
+                self.tags_string(),
+                date_completed.format("%Y-%m-%d %H:%M")
+            );
+        }
+
+        let mut header = format!(
+            "{}{}\x1b[0m \x1b[2;37;37m#{}\x1b[0m{}",
+            importance_color(self.importance),
+            title,
+            self.id,
+            self.tags_string()
+        );
+        if let Some(recurrence) = self.recurrence {
+            header += &format!(" \x1b[2;37;37m({})\x1b[0m", recurrence);
+        }
+
+        if let Some(due_date) = self.due_date {
+            let time_left = crate::date::get_time_left(due_date);
+            let color = if due_date - Local::now() < chrono::Duration::days(1) {
+                "\x1b[1;37;31m"
             } else if due_date - Local::now() < chrono::Duration::weeks(1) {
-                print!("\x1b[1;37;36m");
+                "\x1b[1;37;36m"
             } else {
-                print!("\x1b[1;37;37m");
-            }
-            print!("[due in {}]\x1b[0m", time_left);
+                "\x1b[1;37;37m"
+            };
+            header += &format!("\t{}[due in {}]\x1b[0m", color, time_left);
         }
+        header
     }
 
-    pub fn print_task(&self) {
-        self.print_header();
-        println!();
+    /// Returns the task with its description and sub-tasks.
+    /// The sub-tasks are numbered with paths starting with `path`.
+    pub fn details(&self, path: &str) -> String {
+        let mut text = format!("{}\n", self.header(&[]));
 
         if let Some(desc) = &self.description {
-            println!("{}", desc);
+            text += &format!("{}\n", desc);
         }
 
         if !self.sub_tasks.is_empty() {
-            println!("\x1b[1;37;37mSub tasks ({}):\x1b[0m", self.sub_tasks.len());
+            let done = self.sub_tasks.iter().filter(|t| t.is_done()).count();
+            text += &format!(
+                "\x1b[1;37;37mSub tasks ({}/{} done):\x1b[0m\n",
+                done,
+                self.sub_tasks.len()
+            );
             for (i, task) in self.sub_tasks.iter().enumerate() {
-                print!("{}: ", i + 1);
-                task.print_header();
-                println!();
+                if path.is_empty() {
+                    text += &format!("{}: ", i + 1);
+                } else {
+                    text += &format!("{}.{}: ", path, i + 1);
+                }
+                text += &task.header(&[]);
+                if !task.sub_tasks.is_empty() {
+                    text += &format!(" \x1b[2;37;37m(+{})\x1b[0m", task.sub_tasks.len());
+                }
+                text += "\n";
             }
         }
+        text
     }
 
-    pub fn print_info(&self) {
-        println!("\x1b[1;37;37mTitle:\x1b[0m {}", self.title);
-        let desc = if let Some(desc) = self.description.clone() {
-            desc
+    /// Returns all stored information about the task, one field per line.
+    pub fn info(&self) -> String {
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "None".to_string());
+        let tags = if self.tags.is_empty() {
+            None
         } else {
-            String::from("None")
+            Some(
+                self.tags
+                    .iter()
+                    .map(|tag| format!("+{}", tag))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
         };
-        println!("\x1b[1;37;37mDescription:\x1b[0m {}", desc);
-        println!("\x1b[1;37;37mImportance:\x1b[0m {}", self.importance);
-        println!(
-            "\x1b[1;37;37mDate created:\x1b[0m {}",
-            self.date_created.format("%Y-%m-%d %H:%M:%S")
-        );
-        println!(
-            "\x1b[1;37;37mDate due:\x1b[0m {}",
-            if let Some(due_date) = self.due_date {
-                due_date.format("%Y-%m-%d %H:%M:%S").to_string()
-            } else {
-                "None".to_string()
+        let date =
+            |date: Option<DateFormat>| date.map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string());
+
+        [
+            ("Id", format!("#{}", self.id)),
+            ("Title", self.title.clone()),
+            ("Description", or_none(self.description.clone())),
+            ("Importance", self.importance.to_string()),
+            ("Tags", or_none(tags)),
+            ("Repeats", or_none(self.recurrence.map(|r| r.to_string()))),
+            ("Date created", or_none(date(Some(self.date_created)))),
+            ("Date due", or_none(date(self.due_date))),
+            ("Date completed", or_none(date(self.date_completed))),
+            ("Sub tasks", self.sub_tasks.len().to_string()),
+        ]
+        .iter()
+        .map(|(name, value)| format!("\x1b[1;37;37m{}:\x1b[0m {}\n", name, value))
+        .collect()
+    }
+
+    /// Returns the task in the form it is saved in, with its path added.
+    /// Sub-tasks are left out unless `with_sub_tasks` is set.
+    pub fn to_json(&self, path: &[usize], with_sub_tasks: bool) -> serde_json::Value {
+        let mut json = serde_json::to_value(TaskJson::new(self)).unwrap_or_default();
+        if let Some(object) = json.as_object_mut() {
+            object.insert("path".into(), format_path(path).into());
+            if !with_sub_tasks {
+                object.remove("sub_tasks");
             }
-        );
-        println!("\x1b[1;37;37mSub tasks:\x1b[0m {}", self.sub_tasks.len());
+        }
+        json
     }
 
     pub fn add_sub_task(&mut self, sub_task: Task) {
         self.sub_tasks.push(sub_task);
     }
+
+    /// Returns how many levels the task and its sub-tasks take up.
+    pub fn height(&self) -> usize {
+        1 + self.sub_tasks.iter().map(Task::height).max().unwrap_or(0)
+    }
+}
+
+/// Returns an error if the task would be nested deeper than `MAX_DEPTH` under a parent at `depth`.
+fn check_depth(depth: usize, task: &Task) -> Result<(), String> {
+    if depth + task.height() > MAX_DEPTH {
+        return Err(format!(
+            "Tasks can't be nested more than {} levels deep!",
+            MAX_DEPTH
+        ));
+    }
+    Ok(())
 }
 
 pub struct TaskList {
     pub tasks: Vec<Task>,
-    json_tasks: Vec<TaskJson>,
-    pub last_shown: Option<usize>,
+    next_id: u32,
+    pub journal: Journal,
+    pub config: Config,
+    storage: Box<dyn Storage>,
 }
 
 impl TaskList {
-    pub fn new() -> TaskList {
+    pub fn new(storage: Box<dyn Storage>) -> TaskList {
         TaskList {
             tasks: Vec::new(),
-            json_tasks: Vec::new(),
-            last_shown: None,
+            next_id: 1,
+            journal: Journal::default(),
+            config: Config::default(),
+            storage,
         }
     }
 
-    pub fn add_task(&mut self, task: Task) {
+    pub fn add_task(&mut self, mut task: Task) -> u32 {
+        self.assign_id(&mut task);
+        let id = task.id;
         self.tasks.push(task);
+        id
+    }
+
+    /// Adds a task as a sub-task of the task at `parent` (or as a top level task if it's empty).
+    /// Returns the id of the new task.
+    pub fn insert(&mut self, parent: &[usize], mut task: Task) -> Result<u32, String> {
+        check_depth(parent.len(), &task)?;
+        if parent.is_empty() {
+            return Ok(self.add_task(task));
+        }
+        self.get(parent)?;
+
+        self.assign_id(&mut task);
+        let id = task.id;
+        self.get_mut(parent)?.add_sub_task(task);
+        Ok(id)
+    }
+
+    /// Adds a task right after the task at `path`, as its sibling.
+    /// Returns the id of the new task.
+    pub fn insert_after(&mut self, path: &[usize], mut task: Task) -> Result<u32, String> {
+        self.get(path)?;
+        let (last, parent) = path.split_last().unwrap();
+        check_depth(parent.len(), &task)?;
+
+        self.assign_id(&mut task);
+        let id = task.id;
+        self.children_mut(parent)?.insert(*last, task);
+        Ok(id)
+    }
+
+    /// Assigns ids to the task and its sub-tasks if they don't have one yet.
+    fn assign_id(&mut self, task: &mut Task) {
+        if task.id == 0 {
+            task.id = self.next_id;
+            self.next_id += 1;
+        }
+        for sub_task in task.sub_tasks.iter_mut() {
+            self.assign_id(sub_task);
+        }
+    }
+
+    /// Makes sure every task has a unique id, e.g. after loading tasks saved by older versions.
+    fn assign_missing_ids(&mut self) {
+        // clears duplicate ids so that they get reassigned
+        fn clear_duplicates(tasks: &mut [Task], seen: &mut HashSet<u32>) {
+            for task in tasks.iter_mut() {
+                if !seen.insert(task.id) {
+                    task.id = 0;
+                }
+                clear_duplicates(&mut task.sub_tasks, seen);
+            }
+        }
+
+        let mut seen = HashSet::from([0]);
+        clear_duplicates(&mut self.tasks, &mut seen);
+        self.next_id = self.next_id.max(seen.iter().max().unwrap() + 1);
+
+        let mut tasks = std::mem::take(&mut self.tasks);
+        for task in tasks.iter_mut() {
+            self.assign_id(task);
+        }
+        self.tasks = tasks;
+    }
+
+    /// Returns the path of the task with the specified id.
+    pub fn find_id(&self, id: u32) -> Option<Vec<usize>> {
+        self.flatten()
+            .into_iter()
+            .find(|(_, task)| task.id == id)
+            .map(|(path, _)| path)
+    }
+
+    /// Resolves a task selector: either an id (`#12` or `id:12`) or a path (`3.2.1`).
+    pub fn resolve(&self, selector: &str) -> Result<Vec<usize>, String> {
+        match parse_id(selector) {
+            Some(Ok(id)) => self
+                .find_id(id)
+                .ok_or_else(|| format!("There is no task with id #{}!", id)),
+            Some(Err(e)) => Err(e),
+            None => parse_path(selector),
+        }
+    }
+
+    /// Returns the task at the specified path.
+    pub fn get(&self, path: &[usize]) -> Result<&Task, String> {
+        let mut tasks = &self.tasks;
+        let mut task = None;
+        for (depth, &ind) in path.iter().enumerate() {
+            let t = Self::nth(tasks, &path[..depth], ind)?;
+            tasks = &t.sub_tasks;
+            task = Some(t);
+        }
+        task.ok_or_else(|| "No task specified!".to_string())
+    }
+
+    pub fn get_mut(&mut self, path: &[usize]) -> Result<&mut Task, String> {
+        let (last, parent) = path
+            .split_last()
+            .ok_or_else(|| "No task specified!".to_string())?;
+        let tasks = self.children_mut(parent)?;
+        let len = tasks.len();
+        tasks
+            .get_mut(last - 1)
+            .ok_or_else(|| Self::out_of_range(parent, len))
+    }
+
+    /// Returns the sub-tasks of the task at the specified path.
+    /// An empty path refers to the top level tasks.
+    pub fn children_mut(&mut self, path: &[usize]) -> Result<&mut Vec<Task>, String> {
+        let mut tasks = &mut self.tasks;
+        for (depth, &ind) in path.iter().enumerate() {
+            let len = tasks.len();
+            tasks = match tasks.get_mut(ind - 1) {
+                Some(t) => &mut t.sub_tasks,
+                None => return Err(Self::out_of_range(&path[..depth], len)),
+            };
+        }
+        Ok(tasks)
+    }
+
+    /// Removes the task at the specified path together with its sub-tasks.
+    pub fn remove(&mut self, path: &[usize]) -> Result<Task, String> {
+        let (last, parent) = path
+            .split_last()
+            .ok_or_else(|| "No task specified!".to_string())?;
+        let tasks = self.children_mut(parent)?;
+        if *last > tasks.len() {
+            return Err(Self::out_of_range(parent, tasks.len()));
+        }
+        Ok(tasks.remove(last - 1))
+    }
+
+    fn nth<'a>(tasks: &'a [Task], parent: &[usize], ind: usize) -> Result<&'a Task, String> {
+        tasks
+            .get(ind - 1)
+            .ok_or_else(|| Self::out_of_range(parent, tasks.len()))
+    }
+
+    fn out_of_range(parent: &[usize], len: usize) -> String {
+        if parent.is_empty() {
+            format!("Last index is {}!", len)
+        } else {
+            format!("Last index of task {} is {}!", format_path(parent), len)
+        }
+    }
+
+    /// Returns all tasks and sub-tasks (depth first) together with their paths.
+    pub fn flatten(&self) -> Vec<(Vec<usize>, &Task)> {
+        fn walk<'a>(
+            tasks: &'a [Task],
+            path: &mut Vec<usize>,
+            out: &mut Vec<(Vec<usize>, &'a Task)>,
+        ) {
+            for (i, task) in tasks.iter().enumerate() {
+                path.push(i + 1);
+                out.push((path.clone(), task));
+                walk(&task.sub_tasks, path, out);
+                path.pop();
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(&self.tasks, &mut Vec::new(), &mut out);
+        out
+    }
+
+    /// Returns the tasks that are not done yet.
+    pub fn pending_tasks(&self) -> Vec<Task> {
+        self.tasks
+            .iter()
+            .filter(|t| !t.is_done())
+            .cloned()
+            .collect()
     }
 
     pub fn random_task(&self) -> Option<Task> {
-        if self.tasks.is_empty() {
+        let tasks = self.pending_tasks();
+        if tasks.is_empty() {
             return None;
         }
 
-        Some(Self::get_random(&self.tasks))
+        Some(Self::get_random(&tasks))
     }
 
-    pub fn get_random(task_list: &Vec<Task>) -> Task {
+    pub fn get_random(task_list: &[Task]) -> Task {
         let mut rng = rand::thread_rng();
         let index = rng.gen_range(0..task_list.len());
         task_list[index].clone()
     }
 
+    // done tasks are always sorted after the pending ones
     pub fn sorted_by_importance(&self) -> Vec<Task> {
         let mut tasks = self.tasks.clone();
-        tasks.sort_by(|a, b| b.importance.cmp(&a.importance));
+        tasks.sort_by_key(|t| (t.is_done(), std::cmp::Reverse(t.importance)));
         tasks
     }
 
@@ -190,16 +629,17 @@ impl TaskList {
         let mut tasks = self.tasks.clone();
         // we add 99999 weeks so that the tasks that don't have a due date are at the end
         tasks.sort_by(|a, b| {
-            a.due_date
-                .unwrap_or(Local::now() + Duration::weeks(99999))
-                .cmp(&b.due_date.unwrap_or(Local::now() + Duration::weeks(99999)))
+            a.is_done().cmp(&b.is_done()).then(
+                a.due_date
+                    .unwrap_or(Local::now() + Duration::weeks(99999))
+                    .cmp(&b.due_date.unwrap_or(Local::now() + Duration::weeks(99999))),
+            )
         });
         tasks
     }
 
     pub fn sort_by_date_created(&mut self) {
-        self.tasks
-            .sort_by(|a, b| a.date_created.cmp(&b.date_created));
+        self.tasks.sort_by_key(|a| a.date_created);
     }
 
     pub fn sort_by_due(&mut self) {
@@ -210,142 +650,321 @@ impl TaskList {
         self.tasks = self.sorted_by_importance();
     }
 
+    pub fn sort_by_urgency(&mut self) {
+        let weights = &self.config.urgency;
+        self.tasks.sort_by(|a, b| {
+            a.is_done()
+                .cmp(&b.is_done())
+                .then(b.urgency(weights).total_cmp(&a.urgency(weights)))
+        });
+    }
+
+    /// Returns the most urgent task that can be worked on: one that isn't done
+    /// and has no sub-tasks that aren't done. Tasks at any depth are considered.
+    pub fn most_urgent(&self) -> Option<(Vec<usize>, &Task)> {
+        let weights = &self.config.urgency;
+        self.flatten()
+            .into_iter()
+            .filter(|(_, task)| !task.is_done() && task.sub_tasks.iter().all(|t| t.is_done()))
+            .max_by(|(_, a), (_, b)| a.urgency(weights).total_cmp(&b.urgency(weights)))
+    }
+
     pub fn get_by_importance(&self, importance: u8) -> Vec<Task> {
         let mut tasks = Vec::new();
         for task in self.tasks.iter() {
-            if task.importance == importance {
+            if task.importance == importance && !task.is_done() {
                 tasks.push(task.clone());
             }
         }
         tasks
     }
 
-    pub fn print_tasks(&self) {
-        for (i, task) in self.tasks.iter().enumerate() {
-            print!("{}: ", i + 1);
-            task.print_header();
-            println!();
-        }
+    /// Returns the tasks that are not done yet (without their sub-tasks) with their paths.
+    pub fn pending_roots(&self) -> Vec<(Vec<usize>, &Task)> {
+        self.tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| !task.is_done())
+            .map(|(i, task)| (vec![i + 1], task))
+            .collect()
     }
 
-    fn get_save_file_path() -> Result<PathBuf, std::io::Error> {
-        let mut file_path = std::env::current_exe()?;
-        file_path.pop();
-        file_path.push(SAVE_FILE_NAME);
-        Ok(file_path)
+    /// Returns all tasks and sub-tasks that match the predicate, with their paths.
+    pub fn matching(&self, predicate: impl Fn(&Task) -> bool) -> Vec<(Vec<usize>, &Task)> {
+        let mut tasks = self.flatten();
+        tasks.retain(|(_, task)| predicate(task));
+        tasks
     }
 
-    pub fn save_to_file(&mut self) {
-        let file_path = if let Ok(path) = Self::get_save_file_path() {
-            path
-        } else {
-            println!("Couldn't get save file path");
-            return;
-        };
+    /// Returns all done tasks and sub-tasks, most recently completed first.
+    pub fn done_tasks(&self) -> Vec<(Vec<usize>, &Task)> {
+        let mut done = self.matching(|task| task.is_done());
+        done.sort_by_key(|(_, task)| std::cmp::Reverse(task.date_completed));
+        done
+    }
 
-        let mut file = match std::fs::File::create(&file_path) {
-            Ok(file) => file,
-            Err(e) => {
-                println!("Could not create file: {}", e);
-                return;
-            }
-        };
+    /// Returns the tasks in the form they are saved in.
+    pub fn snapshot(&self) -> Vec<TaskJson> {
+        self.tasks.iter().map(TaskJson::new).collect()
+    }
 
-        self.json_tasks.clear();
-        for t in self.tasks.iter() {
-            self.json_tasks.push(TaskJson::new(&t));
-        }
-        let json = serde_json::to_string(&self.json_tasks).unwrap();
+    /// Replaces all tasks with the ones from a snapshot.
+    pub fn restore(&mut self, snapshot: Vec<TaskJson>) {
+        self.tasks = snapshot.into_iter().map(|t| t.into_task()).collect();
+        self.assign_missing_ids();
+    }
 
-        if let Err(e) = file.write_all(json.as_bytes()) {
-            println!("Could not write to file: {}", e);
-        }
+    pub fn save(&mut self) -> Result<(), String> {
+        let document = Document::new(self.snapshot(), self.next_id);
+        self.storage.save(&document)?;
+        self.storage.save_journal(&self.journal)
     }
 
-    pub fn load_from_file(&mut self) {
-        let file_path = if let Ok(path) = Self::get_save_file_path() {
-            path
-        } else {
-            println!("Couldn't get save file path");
-            return;
-        };
-        let mut file = match std::fs::File::open(&file_path) {
-            Ok(file) => file,
-            Err(e) => {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    if let Ok(_) = std::fs::File::create(&file_path) {
-                        if let Ok(f) = std::fs::File::open(&file_path) {
-                            f
-                        } else {
-                            println!("Could open created file: {}", e);
-                            return;
-                        }
-                    } else {
-                        println!("Could not create file: {}", e);
-                        return;
-                    }
-                } else {
-                    println!("Could not open file: {}", e);
-                    return;
-                }
-            }
-        };
+    pub fn load(&mut self) {
+        self.journal = self.storage.load_journal();
 
-        let mut contents = String::new();
-        if let Err(e) = file.read_to_string(&mut contents) {
-            println!("Could not read file: {}", e);
-            return;
-        }
-        if contents.is_empty() || contents == "[]" {
-            println!("You have no tasks!");
-            return;
+        match self.storage.load() {
+            Ok(document) => {
+                self.next_id = document.next_id.max(1);
+                self.restore(document.tasks);
+            }
+            Err(e) => eprintln!("{}", e),
         }
+    }
 
-        let json: Vec<TaskJson> = match serde_json::from_str(&contents) {
-            Ok(json) => json,
-            Err(e) => {
-                println!("Could not parse file: {}", e);
-                return;
-            }
-        };
+    pub fn storage(&self) -> &dyn Storage {
+        self.storage.as_ref()
+    }
+}
 
-        for t in json {
-            let mut task = Task::new(
-                t.title,
-                if t.description == "None" {
-                    None
-                } else {
-                    Some(t.description)
-                },
-                t.importance,
-                if t.due_date == "None" {
-                    None
-                } else {
-                    Some(t.due_date.parse::<DateFormat>().unwrap())
-                },
-            );
-            task.date_created = t.date_created.parse::<DateFormat>().unwrap();
-
-            for sub_task in t.sub_tasks {
-                let mut s_task = Task::new(
-                    sub_task.title,
-                    if sub_task.description == "None" {
-                        None
-                    } else {
-                        Some(sub_task.description)
-                    },
-                    sub_task.importance,
-                    if sub_task.due_date == "None" {
-                        None
-                    } else {
-                        Some(sub_task.due_date.parse::<DateFormat>().unwrap())
-                    },
-                );
-                s_task.date_created = sub_task.date_created.parse::<DateFormat>().unwrap();
-                task.add_sub_task(s_task);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{JsonFileStorage, MemoryStorage};
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(parse_path("3"), Ok(vec![3]));
+        assert_eq!(parse_path("3.2.1"), Ok(vec![3, 2, 1]));
+        assert!(parse_path("0").is_err(), "Paths are 1-based");
+        assert!(parse_path("3..1").is_err());
+        assert!(parse_path("").is_err());
+        assert_eq!(format_path(&[3, 2, 1]), "3.2.1");
+        assert_eq!(format_count(1), "1 task");
+        assert_eq!(format_count(3), "3 tasks");
+    }
+
+    #[test]
+    fn test_nested_tasks() {
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        task_list.add_task(Task::new("a".into(), None, 0, None));
+        task_list
+            .get_mut(&[1])
+            .unwrap()
+            .add_sub_task(Task::new("b".into(), None, 0, None));
+        task_list
+            .get_mut(&[1, 1])
+            .unwrap()
+            .add_sub_task(Task::new("c".into(), None, 0, None));
+
+        assert_eq!(task_list.get(&[1, 1, 1]).unwrap().title, "c");
+        assert_eq!(
+            task_list.get(&[1, 2]).unwrap_err(),
+            "Last index of task 1 is 1!"
+        );
+        assert_eq!(task_list.flatten().len(), 3);
+
+        let json = TaskJson::new(&task_list.tasks[0]);
+        let task = json.into_task();
+        assert_eq!(
+            task.sub_tasks[0].sub_tasks[0].title, "c",
+            "Grandchildren should survive a reload"
+        );
+
+        assert_eq!(task_list.remove(&[1, 1]).unwrap().title, "b");
+        assert!(task_list.tasks[0].sub_tasks.is_empty());
+    }
 
-            self.tasks.push(task);
+    #[test]
+    fn test_max_depth() {
+        let dir = std::env::temp_dir().join(format!("stuff-depth-test-{}", std::process::id()));
+        let path = dir.join("saved_stuff.json");
+        let mut task_list = TaskList::new(Box::new(JsonFileStorage::new(path.clone())));
+        let mut parent = Vec::new();
+        for i in 0..MAX_DEPTH {
+            let task = Task::new(format!("level {}", i + 1), None, 0, None);
+            task_list.insert(&parent, task).unwrap();
+            parent.push(1);
         }
+        let task = Task::new("too deep".into(), None, 0, None);
+        assert!(task_list.insert(&parent, task.clone()).is_err());
+        parent.pop();
+        let mut with_sub_task = task.clone();
+        with_sub_task.add_sub_task(task);
+        assert!(task_list.insert(&parent, with_sub_task).is_err());
+        task_list.save().unwrap();
+
+        let mut task_list = TaskList::new(Box::new(JsonFileStorage::new(path)));
+        task_list.load();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(task_list.flatten().len(), MAX_DEPTH);
+    }
+
+    #[test]
+    fn test_ids() {
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        task_list.add_task(Task::new("a".into(), None, 0, None));
+        task_list.add_task(Task::new("b".into(), None, 3, None));
+        let id = task_list
+            .insert(&[2], Task::new("c".into(), None, 0, None))
+            .unwrap();
+        assert_eq!(id, 3);
+
+        task_list.sort_by_importance();
+        assert_eq!(
+            task_list.resolve("#3"),
+            Ok(vec![1, 1]),
+            "Ids should survive sorting"
+        );
+        assert_eq!(task_list.resolve("id:1"), Ok(vec![2]));
+        assert_eq!(task_list.resolve("2"), Ok(vec![2]));
+        assert!(task_list.resolve("#4").is_err());
+
+        // tasks saved by older versions have no ids
+        task_list.tasks[0].id = 0;
+        task_list.tasks[1].id = 3;
+        task_list.assign_missing_ids();
+        let mut ids: Vec<u32> = task_list.flatten().iter().map(|(_, t)| t.id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(
+            ids.len(),
+            3,
+            "Missing and duplicate ids should be reassigned"
+        );
+    }
+
+    #[test]
+    fn test_apply_tags() {
+        let mut task = Task::new("".into(), None, 0, None);
+        assert_eq!(
+            task.apply_tags("Fix +Work the build +ci", false),
+            "Fix the build"
+        );
+        assert_eq!(task.tags, BTreeSet::from(["ci".into(), "work".into()]));
+
+        assert_eq!(task.apply_tags("-ci +home - 5", true), "- 5");
+        assert_eq!(task.tags, BTreeSet::from(["home".into(), "work".into()]));
+
+        assert_eq!(
+            task.apply_tags("-home", false),
+            "-home",
+            "Removing needs allow_remove"
+        );
+
+        assert_eq!(
+            task.apply_tags(" +x Pay  rent\tx2 +1 +home ", false),
+            "Pay  rent\tx2 +1",
+            "Tags start with a letter and the spacing of the title is kept"
+        );
+        assert!(!task.tags.contains("1"));
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let mut task = Task::new("a".into(), None, 0, Some(Local::now() - Duration::hours(1)));
+        task.id = 1;
+        task.add_sub_task(Task::new("b".into(), None, 0, task.due_date));
+        assert!(task.next_occurrence().unwrap().is_none(), "Not recurring");
+
+        task.recurrence = Some("every 1w".parse().unwrap());
+        assert!(task.next_occurrence().unwrap().is_none(), "Not done yet");
+
+        task.date_completed = Some(Local::now());
+        task.sub_tasks[0].date_completed = task.date_completed;
+        let next = task.next_occurrence().unwrap().unwrap();
+        assert_eq!(next.id, 0);
+        assert!(!next.is_done() && !next.sub_tasks[0].is_done());
+        assert_eq!(next.due_date, task.due_date.map(|d| d + Duration::weeks(1)));
+        assert_eq!(next.sub_tasks[0].due_date, next.due_date);
+
+        let due = Local.with_ymd_and_hms(2027, 1, 31, 9, 0, 0).unwrap();
+        let mut task = Task::new("c".into(), None, 0, Some(due));
+        task.recurrence = Some("every 1mo".parse().unwrap());
+        task.date_completed = task.due_date;
+        let mut next = task.next_occurrence().unwrap().unwrap();
+        assert_eq!(
+            next.due_date,
+            Local.with_ymd_and_hms(2027, 2, 28, 9, 0, 0).single()
+        );
+        next.date_completed = next.due_date;
+        let next = next.next_occurrence().unwrap().unwrap();
+        assert_eq!(
+            next.due_date,
+            Local.with_ymd_and_hms(2027, 3, 31, 9, 0, 0).single(),
+            "The day of the month should be kept after a shorter month"
+        );
+    }
+
+    #[test]
+    fn test_urgency() {
+        let weights = UrgencyWeights::default();
+        let now = Local::now();
+        let urgency =
+            |importance, due| Task::new("".into(), None, importance, due).urgency(&weights);
+
+        assert!(urgency(3, None) > urgency(2, None));
+        assert!(
+            urgency(0, Some(now + Duration::days(1))) > urgency(0, Some(now + Duration::weeks(1)))
+        );
+        assert_eq!(
+            urgency(0, Some(now - Duration::weeks(2))),
+            weights.due,
+            "Overdue tasks get the full due weight"
+        );
+        assert_eq!(
+            urgency(0, Some(now + Duration::weeks(10))),
+            weights.due * 0.2
+        );
+
+        let mut task = Task::new("".into(), None, 0, None);
+        task.apply_tags("+next", false);
+        assert_eq!(task.urgency(&weights), weights.tags["next"]);
+        task.date_completed = Some(now);
+        assert_eq!(task.urgency(&weights), 0.0, "Done tasks aren't urgent");
+    }
+
+    #[test]
+    fn test_most_urgent() {
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        task_list.add_task(Task::new("a".into(), None, 1, None));
+        task_list.add_task(Task::new("b".into(), None, 3, None));
+        task_list
+            .insert(&[2], Task::new("c".into(), None, 2, None))
+            .unwrap();
+        assert_eq!(
+            task_list.most_urgent().unwrap().0,
+            vec![2, 1],
+            "Tasks with sub-tasks that aren't done can't be worked on"
+        );
+
+        task_list.sort_by_urgency();
+        assert_eq!(task_list.tasks[0].title, "b");
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut task_list = TaskList::new(Box::new(MemoryStorage::default()));
+        task_list.add_task(Task::new("a".into(), None, 0, None));
+        task_list
+            .insert(&[1], Task::new("b".into(), None, 0, None))
+            .unwrap();
+
+        let json = task_list.tasks[0].to_json(&[1], true);
+        assert_eq!(json["path"], "1");
+        assert_eq!(json["id"], 1);
+        assert_eq!(json["sub_tasks"][0]["title"], "b");
+        assert!(task_list.tasks[0].to_json(&[1], false)["sub_tasks"].is_null());
     }
 }
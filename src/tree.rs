@@ -0,0 +1,126 @@
+//! Rendering the task hierarchy as a tree with box-drawing connectors.
+
+use crate::interface::truncate_to_width;
+use crate::task::{format_path, Task};
+
+/// Returns how many of the sub-tasks of the task (at any depth) are done, and how many there are.
+pub fn progress(task: &Task) -> (usize, usize) {
+    task.sub_tasks
+        .iter()
+        .fold((0, 0), |(done, total), sub_task| {
+            let (sub_done, sub_total) = progress(sub_task);
+            (
+                done + sub_done + sub_task.is_done() as usize,
+                total + sub_total + 1,
+            )
+        })
+}
+
+/// Renders the tasks and their sub-tasks. Tasks deeper than `max_depth` (1 for only the roots)
+/// are left out and lines are cut to `width`.
+pub fn render(
+    roots: &[(Vec<usize>, &Task)],
+    max_depth: Option<usize>,
+    width: usize,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (path, task) in roots {
+        let mut path = path.clone();
+        render_node(task, &mut path, "", "", 1, max_depth, width, &mut lines);
+    }
+    lines
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_node(
+    task: &Task,
+    path: &mut Vec<usize>,
+    connector: &str,
+    indent: &str,
+    depth: usize,
+    max_depth: Option<usize>,
+    width: usize,
+    lines: &mut Vec<String>,
+) {
+    let mut line = format!(
+        "{}{}: {}",
+        connector,
+        format_path(path),
+        task.header(&[]).replace('\t', " ")
+    );
+    if !task.sub_tasks.is_empty() {
+        let (done, total) = progress(task);
+        line += &format!(" \x1b[2;37;37m({}/{} done)\x1b[0m", done, total);
+    }
+    let expand = max_depth.is_none_or(|max_depth| depth < max_depth);
+    if !expand && !task.sub_tasks.is_empty() {
+        line += &format!(" \x1b[2;37;37m(+{})\x1b[0m", task.sub_tasks.len());
+    }
+    lines.push(truncate_to_width(&line, width));
+    if !expand {
+        return;
+    }
+
+    for (i, sub_task) in task.sub_tasks.iter().enumerate() {
+        let last = i + 1 == task.sub_tasks.len();
+        let (connector, child_indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        path.push(i + 1);
+        render_node(
+            sub_task,
+            path,
+            &format!("{}{}", indent, connector),
+            &format!("{}{}", indent, child_indent),
+            depth + 1,
+            max_depth,
+            width,
+            lines,
+        );
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::{strip_colors, visible_width};
+
+    fn tree() -> Task {
+        let mut root = Task::new("a".into(), None, 0, None);
+        let mut b = Task::new("b".into(), None, 0, None);
+        b.add_sub_task(Task::new("c".into(), None, 0, None));
+        b.date_completed = Some(chrono::Local::now());
+        root.add_sub_task(b);
+        root.add_sub_task(Task::new("d".into(), None, 0, None));
+        root
+    }
+
+    #[test]
+    fn test_progress() {
+        assert_eq!(progress(&tree()), (1, 3));
+    }
+
+    #[test]
+    fn test_render() {
+        let root = tree();
+        let lines: Vec<String> = render(&[(vec![1], &root)], None, 80)
+            .iter()
+            .map(|line| strip_colors(line))
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("1: a #0 (1/3 done)"));
+        assert!(lines[1].starts_with("├── 1.1: b"));
+        assert!(lines[2].starts_with("│   └── 1.1.1: c"));
+        assert!(lines[3].starts_with("└── 1.2: d"));
+
+        let lines = render(&[(vec![1], &root)], Some(1), 80);
+        assert_eq!(lines.len(), 1);
+        assert!(strip_colors(&lines[0]).ends_with("(+2)"));
+
+        let lines = render(&[(vec![1], &root)], None, 12);
+        assert!(lines.iter().all(|line| visible_width(line) <= 12));
+    }
+}